@@ -1,9 +1,20 @@
+// The IDL instructions #[program] generates in anchor 0.31 call AccountInfo::realloc from
+// `__private::__idl` at the crate root, which no item-level attribute can reach. They are
+// only compiled without `no-idl`, and the handlers below re-enable the lint.
+#![cfg_attr(not(feature = "no-idl"), allow(deprecated))]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
 use anchor_lang::system_program;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::{TransferFeeAmount, TransferFeeConfig};
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::state::{Account as TokenAccountState, Mint as MintState};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
 
 declare_id!("8sRBcQiawsPTmLAcoJPtGAf4gYEszqHLx31DZEtjcinb");
 
 #[program]
+#[warn(deprecated)]
 pub mod agreed_contracts {
     use super::*;
 
@@ -28,6 +39,7 @@ pub mod agreed_contracts {
         reputation.quality_ratings_sum = 0;
         reputation.quality_ratings_count = 0;
         reputation.dispute_count_vendor = 0;
+        reputation.missed_deadlines_vendor = 0;
         
         // Client reputation
         reputation.client_score = 0;
@@ -41,6 +53,7 @@ pub mod agreed_contracts {
         
         // Cross-role metrics
         reputation.total_value_transacted = 0;
+        reputation.token_volumes = Vec::new();
         reputation.first_activity = clock.unix_timestamp;
         reputation.last_activity = clock.unix_timestamp;
         reputation.bump = ctx.bumps.reputation;
@@ -49,48 +62,92 @@ pub mod agreed_contracts {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_contract(
         ctx: Context<InitializeContract>,
         contract_id: u64,
-        participants: Vec<Pubkey>,
-        required_approvals: u8,
+        participants: Vec<Participant>,
+        required_weight: u32,
+        veto_threshold: u8,
+        arbiter: Option<Pubkey>,
+        approval_deadline: Option<i64>,
+        ordered_signing: bool,
     ) -> Result<()> {
         require!(
-            participants.len() <= Contract::MAX_PARTICIPANTS,
-            ErrorCode::TooManyParticipants
-        );
-        require!(
-            required_approvals as usize <= participants.len(),
-            ErrorCode::InvalidApprovalThreshold
+            ctx.accounts.first_amendment.data_is_empty(),
+            ErrorCode::ContractIdReused
         );
         require!(
-            participants.contains(&ctx.accounts.creator.key()),
-            ErrorCode::CreatorMustBeParticipant
+            participants.len() <= Contract::MAX_PARTICIPANTS,
+            ErrorCode::TooManyParticipants
         );
+        for (i, participant) in participants.iter().enumerate() {
+            participant.validate()?;
+            require!(
+                !participants[..i].iter().any(|other| other.wallet == participant.wallet),
+                ErrorCode::AlreadyAParticipant
+            );
+        }
+        let creator_role = participants
+            .iter()
+            .find(|participant| participant.wallet == ctx.accounts.creator.key())
+            .map(|participant| participant.role)
+            .ok_or(ErrorCode::CreatorMustBeParticipant)?;
+        if let Some(arbiter) = &arbiter {
+            require!(
+                !participants.iter().any(|participant| participant.wallet == *arbiter),
+                ErrorCode::ArbiterIsParticipant
+            );
+        }
+        if let Some(approval_deadline) = approval_deadline {
+            require!(
+                approval_deadline > Clock::get()?.unix_timestamp,
+                ErrorCode::InvalidDeadline
+            );
+        }
 
         let contract = &mut ctx.accounts.contract;
         contract.contract_id = contract_id;
         contract.creator = ctx.accounts.creator.key();
         contract.participants = participants;
+        require!(
+            required_weight > 0 && required_weight <= contract.total_weight(),
+            ErrorCode::InvalidApprovalThreshold
+        );
+        require!(
+            veto_threshold > 0 && veto_threshold as usize <= contract.approver_count(),
+            ErrorCode::InvalidVetoThreshold
+        );
         contract.status = ContractStatus::Active;
-        contract.required_approvals = required_approvals;
-        contract.current_approvals = 0;
+        contract.required_weight = required_weight;
+        contract.current_weight = 0;
+        contract.veto_threshold = veto_threshold;
+        contract.rejectors = Vec::new();
+        contract.approval_deadline = approval_deadline;
+        contract.milestone_count = 0;
+        contract.ordered_signing = ordered_signing;
+        contract.open_milestones = Vec::new();
+        contract.pending_proposals = 0;
         contract.approvers = Vec::new();
-        contract.ipfs_hash = String::new();
+        contract.document = None;
         contract.created_at = Clock::get()?.unix_timestamp;
         contract.bump = ctx.bumps.contract;
+        contract.arbiter = arbiter;
+        contract.version = 0;
+        contract.previous_version_hash = [0; 32];
 
-        // Update creator's reputation (creator acts as vendor when creating contract)
         let creator_rep = &mut ctx.accounts.creator_reputation;
         creator_rep.contracts_created += 1;
-        creator_rep.deals_as_vendor += 1;
+        creator_rep.record_deal_as(creator_role);
         creator_rep.last_activity = Clock::get()?.unix_timestamp;
 
         msg!("Contract {} created by {}", contract_id, contract.creator);
         Ok(())
     }
 
-    pub fn approve_contract(ctx: Context<ApproveContract>) -> Result<()> {
+    /// Approves the contract's current document, identified by its `DocumentRef::fingerprint`.
+    /// Approvals for an older version are refused, and amendments clear earlier approvals.
+    pub fn approve_contract(ctx: Context<ApproveContract>, document_hash: [u8; 32]) -> Result<()> {
         let contract = &mut ctx.accounts.contract;
         let approver = ctx.accounts.approver.key();
 
@@ -99,30 +156,118 @@ pub mod agreed_contracts {
             ErrorCode::ContractNotActive
         );
         require!(
-            contract.participants.contains(&approver),
-            ErrorCode::NotAParticipant
+            contract.approval_window_open(Clock::get()?.unix_timestamp),
+            ErrorCode::ApprovalDeadlinePassed
         );
+        let role = contract.require_approver(&approver)?;
         require!(
             !contract.approvers.contains(&approver),
             ErrorCode::AlreadyApproved
         );
+        require!(
+            !contract.rejectors.contains(&approver),
+            ErrorCode::AlreadyRejected
+        );
+        if contract.ordered_signing {
+            require!(
+                contract.next_signer() == Some(approver),
+                ErrorCode::SignedOutOfTurn
+            );
+        }
+        let document = contract.document.as_ref().ok_or(ErrorCode::NoDocumentToApprove)?;
+        require!(
+            document.fingerprint() == document_hash,
+            ErrorCode::DocumentHashMismatch
+        );
 
         contract.approvers.push(approver);
-        contract.current_approvals += 1;
+        contract.current_weight += contract.weight_of(&approver);
 
-        // Update approver's reputation (approver acts as client when approving)
+        // The creator's deal was counted when the contract was created
         let approver_rep = &mut ctx.accounts.approver_reputation;
         approver_rep.contracts_approved += 1;
-        approver_rep.deals_as_client += 1;
+        if approver != contract.creator {
+            approver_rep.record_deal_as(role);
+        }
         approver_rep.last_activity = Clock::get()?.unix_timestamp;
 
-        let was_completed = contract.current_approvals >= contract.required_approvals;
+        let was_completed = contract.meets_threshold(contract.current_weight);
         if was_completed {
             contract.status = ContractStatus::Completed;
             msg!("Contract {} completed!", contract.contract_id);
         }
 
-        msg!("Contract {} approved by {}", contract.contract_id, approver);
+        msg!("Contract {} version {} approved by {}", contract.contract_id, contract.version, approver);
+        Ok(())
+    }
+
+    /// Withdraws an approval while the contract is still active.
+    pub fn revoke_approval(ctx: Context<RevokeApproval>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let approver = ctx.accounts.approver.key();
+
+        require!(
+            contract.status == ContractStatus::Active,
+            ErrorCode::ContractNotActive
+        );
+        let role = contract.require_approver(&approver)?;
+        require!(
+            contract.approvers.contains(&approver),
+            ErrorCode::NotApproved
+        );
+
+        contract.approvers.retain(|wallet| *wallet != approver);
+        contract.current_weight -= contract.weight_of(&approver);
+
+        // Undo what approve_contract recorded so a later approval isn't double counted
+        let approver_rep = &mut ctx.accounts.approver_reputation;
+        approver_rep.contracts_approved = approver_rep.contracts_approved.saturating_sub(1);
+        if approver != contract.creator {
+            approver_rep.forget_deal_as(role);
+        }
+        approver_rep.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!("Contract {} approval revoked by {}", contract.contract_id, approver);
+        Ok(())
+    }
+
+    /// Records a "no" vote. Once `veto_threshold` participants have rejected, the contract
+    /// moves to `Rejected`. An approver has to revoke their approval before rejecting.
+    pub fn reject_contract(ctx: Context<RejectContract>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let rejector = ctx.accounts.rejector.key();
+
+        require!(
+            contract.status == ContractStatus::Active,
+            ErrorCode::ContractNotActive
+        );
+        require!(
+            contract.approval_window_open(Clock::get()?.unix_timestamp),
+            ErrorCode::ApprovalDeadlinePassed
+        );
+        contract.require_approver(&rejector)?;
+        require!(
+            !contract.approvers.contains(&rejector),
+            ErrorCode::AlreadyApproved
+        );
+        require!(
+            !contract.rejectors.contains(&rejector),
+            ErrorCode::AlreadyRejected
+        );
+
+        contract.rejectors.push(rejector);
+        msg!(
+            "Contract {} rejected by {} ({}/{})",
+            contract.contract_id,
+            rejector,
+            contract.rejectors.len(),
+            contract.veto_threshold
+        );
+
+        if contract.rejectors.len() >= contract.veto_threshold as usize {
+            contract.status = ContractStatus::Rejected;
+            msg!("Contract {} vetoed", contract.contract_id);
+        }
         Ok(())
     }
 
@@ -136,10 +281,18 @@ pub mod agreed_contracts {
             ErrorCode::ContractNotCompleted
         );
 
+        let role = contract
+            .role_of(&ctx.accounts.participant.key())
+            .ok_or(ErrorCode::NotAParticipant)?;
+
         // Update participant's completion count
         let participant_rep = &mut ctx.accounts.participant_reputation;
         participant_rep.contracts_completed += 1;
-        participant_rep.completed_as_vendor += 1;
+        match role {
+            ParticipantRole::Vendor => participant_rep.completed_as_vendor += 1,
+            ParticipantRole::Client => participant_rep.completed_as_client += 1,
+            ParticipantRole::Reviewer | ParticipantRole::Observer => {}
+        }
         participant_rep.last_activity = Clock::get()?.unix_timestamp;
 
         msg!("Marked complete for participant: {}", participant_rep.wallet);
@@ -160,69 +313,337 @@ pub mod agreed_contracts {
 
         contract.status = ContractStatus::Cancelled;
 
-        // Update creator reputation (creator acts as vendor when creating contract)
         let creator_rep = &mut ctx.accounts.creator_reputation;
-        creator_rep.cancelled_as_vendor += 1;
+        match contract.role_of(&contract.creator) {
+            Some(ParticipantRole::Vendor) => creator_rep.cancelled_as_vendor += 1,
+            Some(ParticipantRole::Client) => creator_rep.cancelled_as_client += 1,
+            _ => {}
+        }
         creator_rep.last_activity = Clock::get()?.unix_timestamp;
 
         Ok(())
     }
 
-    pub fn update_contract_ipfs(ctx: Context<UpdateContractIpfs>, ipfs_hash: String) -> Result<()> {
+    /// Permissionless: once an active contract is past its approval deadline, anyone can
+    /// mark it `Expired`. The deals counted for the creator and approvers are reversed, so
+    /// pass each approver's reputation account (other than the creator's) in approval order
+    /// as remaining accounts.
+    pub fn expire_contract<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExpireContract<'info>>,
+    ) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+
+        require!(
+            contract.status == ContractStatus::Active,
+            ErrorCode::ContractNotActive
+        );
+        require!(
+            !contract.approval_window_open(Clock::get()?.unix_timestamp),
+            ErrorCode::ApprovalDeadlineNotPassed
+        );
+
+        contract.status = ContractStatus::Expired;
+
+        if let Some(role) = contract.role_of(&contract.creator) {
+            ctx.accounts.creator_reputation.forget_deal_as(role);
+        }
+
+        let mut reputation_accounts = ctx.remaining_accounts.iter();
+        for approver in contract.approvers.iter().filter(|approver| **approver != contract.creator) {
+            let Some(reputation_info) = reputation_accounts.next() else {
+                return err!(ErrorCode::InvalidApproverReputation);
+            };
+            require_keys_eq!(*reputation_info.owner, crate::ID, ErrorCode::InvalidApproverReputation);
+            let mut data = reputation_info.try_borrow_mut_data()?;
+            let mut reputation = UserReputation::try_deserialize(&mut &data[..])?;
+            require_keys_eq!(reputation.wallet, *approver, ErrorCode::InvalidApproverReputation);
+            if let Some(role) = contract.role_of(approver) {
+                reputation.forget_deal_as(role);
+            }
+            reputation.try_serialize(&mut &mut data[..])?;
+        }
+
+        msg!("Contract {} expired with {}/{} approval weight", contract.contract_id, contract.current_weight, contract.required_weight);
+        Ok(())
+    }
+
+    /// Closes an expired, cancelled or rejected contract and returns its rent to the creator.
+    /// Contracts that ever funded a milestone stay open so those milestones can settle.
+    /// Pending amendments and participant changes must be withdrawn first, or they could be
+    /// approved against a later contract reusing the same id.
+    pub fn close_contract(ctx: Context<CloseContract>) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+
+        require!(
+            matches!(
+                contract.status,
+                ContractStatus::Expired | ContractStatus::Cancelled | ContractStatus::Rejected
+            ),
+            ErrorCode::ContractStillOpen
+        );
+        require!(
+            contract.milestone_count == 0,
+            ErrorCode::ContractHasMilestones
+        );
         require!(
-            ipfs_hash.len() <= 46,
-            ErrorCode::IpfsHashTooLong
+            contract.pending_proposals == 0,
+            ErrorCode::ContractHasPendingProposals
         );
+
+        msg!("Contract {} closed", contract.contract_id);
+        Ok(())
+    }
+
+    /// Sets the contract's initial document (version 1). Later changes go through
+    /// `propose_contract_amendment`.
+    pub fn set_contract_document(ctx: Context<SetContractDocument>, document: DocumentRef) -> Result<()> {
+        document.validate()?;
         
         let contract = &mut ctx.accounts.contract;
         let updater = ctx.accounts.updater.key();
 
+        contract.require_approver(&updater)?;
         require!(
-            contract.participants.contains(&updater),
-            ErrorCode::NotAParticipant
+            contract.version == 0,
+            ErrorCode::DocumentAlreadySet
         );
 
-        contract.ipfs_hash = ipfs_hash;
+        contract.document = Some(document);
+        contract.version = 1;
         
-        msg!("Contract {} IPFS hash updated to {}", contract.contract_id, contract.ipfs_hash);
+        msg!("Contract {} document set", contract.contract_id);
         Ok(())
     }
 
-    // ========== ESCROW MILESTONE FUNCTIONS ==========
+    // ========== CONTRACT AMENDMENT FUNCTIONS ==========
 
-    pub fn initialize_escrow_milestone(
-        ctx: Context<InitializeEscrowMilestone>,
-        milestone_id: u64,
-        contract_id: u64,
-        description: String,
-        amount: u64,
-        recipient: Pubkey,
-        deadline: i64,
+    /// Proposes the next version of the contract document. The proposer's approval is
+    /// counted straight away; if their weight alone applies it, the reputations of the
+    /// contract's current approvers follow as remaining accounts.
+    pub fn propose_contract_amendment<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProposeContractAmendment<'info>>,
+        document: DocumentRef,
     ) -> Result<()> {
+        document.validate()?;
+
+        let contract = &ctx.accounts.contract;
+        let proposer = ctx.accounts.proposer.key();
         require!(
-            description.len() <= 200,
-            ErrorCode::DescriptionTooLong
+            !matches!(
+                contract.status,
+                ContractStatus::Cancelled | ContractStatus::Rejected | ContractStatus::Expired
+            ),
+            ErrorCode::ContractNotActive
         );
-        
+        contract.require_approver(&proposer)?;
+        let previous_document = contract.document.clone().ok_or(ErrorCode::NoDocumentToAmend)?;
+
+        let amendment = &mut ctx.accounts.amendment;
+        amendment.contract = contract.key();
+        amendment.proposer = proposer;
+        amendment.version = contract.version + 1;
+        amendment.previous_document = previous_document;
+        amendment.document = document;
+        amendment.approvals = vec![proposer];
+        amendment.status = AmendmentStatus::Pending;
+        amendment.created_at = Clock::get()?.unix_timestamp;
+        amendment.applied_at = None;
+        amendment.bump = ctx.bumps.amendment;
+
+        if contract.meets_threshold(contract.approval_weight(&amendment.approvals)) {
+            apply_contract_amendment(&mut ctx.accounts.contract, amendment, ctx.remaining_accounts)?;
+        } else {
+            ctx.accounts.contract.pending_proposals += 1;
+        }
+
+        msg!("Contract {} amendment to version {} proposed by {}", ctx.accounts.contract.contract_id, amendment.version, proposer);
+        Ok(())
+    }
+
+    /// Approves a pending amendment; it is applied once its approvers' combined weight
+    /// reaches `required_weight`. The approval that applies it passes the contract's current
+    /// approvers' reputations as remaining accounts, in `approvers` order.
+    pub fn approve_contract_amendment<'info>(
+        ctx: Context<'_, '_, '_, 'info, ApproveContractAmendment<'info>>,
+    ) -> Result<()> {
         let contract = &ctx.accounts.contract;
+        let amendment = &mut ctx.accounts.amendment;
+        let approver = ctx.accounts.approver.key();
+
         require!(
-            contract.status == ContractStatus::Active || contract.status == ContractStatus::Completed,
+            !matches!(
+                contract.status,
+                ContractStatus::Cancelled | ContractStatus::Rejected | ContractStatus::Expired
+            ),
             ErrorCode::ContractNotActive
         );
         require!(
-            contract.creator == ctx.accounts.creator.key(),
-            ErrorCode::OnlyCreatorCanInitializeEscrow
+            amendment.status == AmendmentStatus::Pending,
+            ErrorCode::AmendmentNotPending
+        );
+        contract.require_approver(&approver)?;
+        require!(
+            !amendment.approvals.contains(&approver),
+            ErrorCode::AlreadyApprovedAmendment
+        );
+
+        amendment.approvals.push(approver);
+        msg!(
+            "Contract {} amendment to version {} approved by {} ({}/{})",
+            contract.contract_id,
+            amendment.version,
+            approver,
+            contract.approval_weight(&amendment.approvals),
+            contract.required_weight
         );
+
+        if contract.meets_threshold(contract.approval_weight(&amendment.approvals)) {
+            apply_contract_amendment(&mut ctx.accounts.contract, amendment, ctx.remaining_accounts)?;
+            ctx.accounts.contract.pending_proposals -= 1;
+        }
+        Ok(())
+    }
+
+    /// Lets the proposer drop a pending amendment so another can be proposed for that version.
+    pub fn withdraw_contract_amendment(
+        ctx: Context<WithdrawContractAmendment>,
+    ) -> Result<()> {
         require!(
-            contract.participants.contains(&recipient),
-            ErrorCode::RecipientNotParticipant
+            ctx.accounts.amendment.status == AmendmentStatus::Pending,
+            ErrorCode::AmendmentNotPending
+        );
+        ctx.accounts.contract.pending_proposals -= 1;
+        msg!(
+            "Contract {} amendment to version {} withdrawn",
+            ctx.accounts.contract.contract_id,
+            ctx.accounts.amendment.version
         );
+        Ok(())
+    }
+
+    // ========== PARTICIPANT CHANGE FUNCTIONS ==========
+
+    /// Proposes adding or removing `participant`. The proposer's approval is counted
+    /// straight away; the change applies once it meets the contract's approval threshold.
+    pub fn propose_participant_change(
+        ctx: Context<ProposeParticipantChange>,
+        participant: Pubkey,
+        action: ParticipantAction,
+    ) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        let proposer = ctx.accounts.proposer.key();
         require!(
-            amount > 0,
-            ErrorCode::InvalidAmount
+            !matches!(
+                contract.status,
+                ContractStatus::Cancelled | ContractStatus::Rejected | ContractStatus::Expired
+            ),
+            ErrorCode::ContractNotActive
+        );
+        contract.require_approver(&proposer)?;
+
+        let change = &mut ctx.accounts.participant_change;
+        change.contract = contract.key();
+        change.proposer = proposer;
+        change.participant = participant;
+        change.action = action;
+        change.approvals = vec![proposer];
+        change.created_at = Clock::get()?.unix_timestamp;
+        change.bump = ctx.bumps.participant_change;
+        validate_participant_change(contract, change)?;
+
+        msg!("Contract {} participant change for {} proposed by {}", contract.contract_id, participant, proposer);
+
+        if contract.meets_threshold(contract.approval_weight(&change.approvals)) {
+            apply_participant_change(&mut ctx.accounts.contract, change)?;
+            ctx.accounts.participant_change.close(ctx.accounts.proposer.to_account_info())?;
+        } else {
+            ctx.accounts.contract.pending_proposals += 1;
+        }
+        Ok(())
+    }
+
+    /// Approves a pending participant change and applies it (closing the proposal) once
+    /// enough current participants have approved.
+    pub fn approve_participant_change(
+        ctx: Context<ApproveParticipantChange>,
+    ) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        let change = &mut ctx.accounts.participant_change;
+        let approver = ctx.accounts.approver.key();
+
+        contract.require_approver(&approver)?;
+        require!(
+            !change.approvals.contains(&approver),
+            ErrorCode::AlreadyApproved
+        );
+
+        // Approvals from since-removed participants no longer count and would crowd the
+        // fixed-size approvals list
+        change.approvals.retain(|wallet| contract.is_participant(wallet));
+        change.approvals.push(approver);
+        let weight = contract.approval_weight(&change.approvals);
+        msg!(
+            "Contract {} participant change for {} approved by {} ({}/{})",
+            contract.contract_id,
+            change.participant,
+            approver,
+            weight,
+            contract.required_weight
+        );
+
+        if contract.meets_threshold(weight) {
+            apply_participant_change(&mut ctx.accounts.contract, change)?;
+            ctx.accounts.contract.pending_proposals -= 1;
+            ctx.accounts.participant_change.close(ctx.accounts.proposer.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    pub fn withdraw_participant_change(
+        ctx: Context<WithdrawParticipantChange>,
+    ) -> Result<()> {
+        ctx.accounts.contract.pending_proposals -= 1;
+        msg!(
+            "Contract {} participant change for {} withdrawn",
+            ctx.accounts.contract.contract_id,
+            ctx.accounts.participant_change.participant
         );
+        Ok(())
+    }
+
+    // ========== ESCROW MILESTONE FUNCTIONS ==========
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_escrow_milestone(
+        ctx: Context<InitializeEscrowMilestone>,
+        milestone_id: u64,
+        contract_id: u64,
+        description: String,
+        amount: u64,
+        recipient: Pubkey,
+        deadline: i64,
+        late_penalty: Option<LatePenalty>,
+        early_bonus: Option<EarlyBonus>,
+        retention: Option<Retention>,
+        splits: Vec<RecipientShare>,
+    ) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        validate_escrow_terms(contract, &ctx.accounts.creator.key(), &description, amount, &recipient)?;
+        validate_recipient_splits(contract, &ctx.accounts.creator.key(), &recipient, &splits)?;
+        // A deadline already behind us would let the milestone be expired against the vendor at once
+        require!(deadline > Clock::get()?.unix_timestamp, ErrorCode::InvalidDeadline);
+        if let Some(late_penalty) = &late_penalty {
+            late_penalty.validate()?;
+        }
+        if let Some(retention) = &retention {
+            retention.validate()?;
+        }
+        if let Some(early_bonus) = &early_bonus {
+            early_bonus.validate()?;
+        }
+        let bonus_amount = early_bonus.as_ref().map_or(0, |bonus| bonus.amount);
 
-        // Transfer SOL to escrow PDA first
+        // Transfer SOL (plus any early-delivery bonus) to escrow PDA first
         let cpi_context = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             system_program::Transfer {
@@ -230,30 +651,47 @@ pub mod agreed_contracts {
                 to: ctx.accounts.escrow_milestone.to_account_info(),
             },
         );
-        system_program::transfer(cpi_context, amount)?;
+        system_program::transfer(cpi_context, amount + bonus_amount)?;
 
         // Now initialize the escrow data
         let escrow = &mut ctx.accounts.escrow_milestone;
         escrow.milestone_id = milestone_id;
         escrow.contract_id = contract_id;
+        escrow.contract = contract.key();
         escrow.description = description;
         escrow.amount = amount;
         escrow.recipient = recipient;
         escrow.deadline = deadline;
         escrow.status = MilestoneStatus::Funded;
-        escrow.approvals_required = contract.participants.len() as u8;
+        escrow.approvals_required = contract.approver_count() as u8;
         escrow.approvals = Vec::new();
         escrow.marked_complete_by = None;
         escrow.creator = ctx.accounts.creator.key();
         escrow.created_at = Clock::get()?.unix_timestamp;
+        escrow.mint = None;
+        escrow.amount_delivered = 0;
+        escrow.released_amount = 0;
+        escrow.late_penalty = late_penalty;
+        escrow.completed_at = None;
+        escrow.penalty_amount = 0;
+        escrow.early_bonus = early_bonus;
+        escrow.retention = retention;
+        escrow.retained_amount = 0;
+        escrow.settled_at = None;
+        escrow.disputed = false;
+        escrow.splits = splits;
+        escrow.amendment_count = 0;
+        escrow.dispute_count = 0;
         escrow.bump = ctx.bumps.escrow_milestone;
 
         // Update creator reputation (creator acts as client when funding milestone)
         let creator_rep = &mut ctx.accounts.creator_reputation;
-        creator_rep.total_value_escrowed += amount;
-        creator_rep.total_value_transacted += amount;
+        creator_rep.total_value_escrowed += amount + bonus_amount;
+        creator_rep.record_value_transacted(None, amount);
         creator_rep.deals_as_client += 1;
         creator_rep.last_activity = Clock::get()?.unix_timestamp;
+        ctx.accounts.contract.milestone_count += 1;
+        ctx.accounts.contract.open_milestone(&ctx.accounts.escrow_milestone);
 
         // Note: Recipient's deals_as_vendor will be tracked when milestone is marked complete
         // This avoids requiring recipient_reputation account at creation time
@@ -262,6 +700,102 @@ pub mod agreed_contracts {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_token_escrow_milestone(
+        ctx: Context<InitializeTokenEscrowMilestone>,
+        milestone_id: u64,
+        contract_id: u64,
+        description: String,
+        amount: u64,
+        recipient: Pubkey,
+        deadline: i64,
+        late_penalty: Option<LatePenalty>,
+        early_bonus: Option<EarlyBonus>,
+        retention: Option<Retention>,
+        splits: Vec<RecipientShare>,
+    ) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        validate_escrow_terms(contract, &ctx.accounts.creator.key(), &description, amount, &recipient)?;
+        validate_recipient_splits(contract, &ctx.accounts.creator.key(), &recipient, &splits)?;
+        // A deadline already behind us would let the milestone be expired against the vendor at once
+        require!(deadline > Clock::get()?.unix_timestamp, ErrorCode::InvalidDeadline);
+        validate_escrow_mint(&ctx.accounts.mint)?;
+        if let Some(late_penalty) = &late_penalty {
+            late_penalty.validate()?;
+        }
+        if let Some(retention) = &retention {
+            retention.validate()?;
+        }
+        let mut early_bonus = early_bonus;
+        if let Some(early_bonus) = &early_bonus {
+            early_bonus.validate()?;
+        }
+
+        // Move tokens from the creator into the vault owned by the escrow PDA. Transfer-fee
+        // mints withhold part of each deposit, so the escrow holds what the vault received.
+        let amount = deposit_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.creator_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.creator,
+            amount,
+        )?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        if let Some(early_bonus) = &mut early_bonus {
+            early_bonus.amount = deposit_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.creator_token_account,
+                &ctx.accounts.mint,
+                &ctx.accounts.vault,
+                &ctx.accounts.creator,
+                early_bonus.amount,
+            )?;
+        }
+
+        let mint = ctx.accounts.mint.key();
+        let escrow = &mut ctx.accounts.escrow_milestone;
+        escrow.milestone_id = milestone_id;
+        escrow.contract_id = contract_id;
+        escrow.contract = contract.key();
+        escrow.description = description;
+        escrow.amount = amount;
+        escrow.recipient = recipient;
+        escrow.deadline = deadline;
+        escrow.status = MilestoneStatus::Funded;
+        escrow.approvals_required = contract.approver_count() as u8;
+        escrow.approvals = Vec::new();
+        escrow.marked_complete_by = None;
+        escrow.creator = ctx.accounts.creator.key();
+        escrow.created_at = Clock::get()?.unix_timestamp;
+        escrow.mint = Some(mint);
+        escrow.amount_delivered = 0;
+        escrow.released_amount = 0;
+        escrow.late_penalty = late_penalty;
+        escrow.completed_at = None;
+        escrow.penalty_amount = 0;
+        escrow.early_bonus = early_bonus;
+        escrow.retention = retention;
+        escrow.retained_amount = 0;
+        escrow.settled_at = None;
+        escrow.disputed = false;
+        escrow.splits = splits;
+        escrow.amendment_count = 0;
+        escrow.dispute_count = 0;
+        escrow.bump = ctx.bumps.escrow_milestone;
+
+        // total_value_escrowed stays denominated in lamports; token value is tracked per mint
+        let creator_rep = &mut ctx.accounts.creator_reputation;
+        creator_rep.record_value_transacted(Some(mint), amount);
+        creator_rep.deals_as_client += 1;
+        creator_rep.last_activity = Clock::get()?.unix_timestamp;
+        ctx.accounts.contract.milestone_count += 1;
+        ctx.accounts.contract.open_milestone(&ctx.accounts.escrow_milestone);
+
+        msg!("Escrow milestone {} created and funded with {} tokens of mint {}", milestone_id, amount, mint);
+        Ok(())
+    }
+
     pub fn mark_milestone_complete(
         ctx: Context<MarkMilestoneComplete>,
     ) -> Result<()> {
@@ -276,7 +810,7 @@ pub mod agreed_contracts {
 
         let contract = &ctx.accounts.contract;
         require!(
-            contract.participants.contains(&marker),
+            contract.is_participant(&marker),
             ErrorCode::NotAParticipant
         );
         require!(
@@ -285,15 +819,19 @@ pub mod agreed_contracts {
         );
 
         escrow.marked_complete_by = Some(marker);
+        escrow.completed_at = Some(clock.unix_timestamp);
         escrow.status = MilestoneStatus::MarkedComplete;
 
         // Update vendor (recipient) reputation - track delivery time and deal start
         let vendor_rep = &mut ctx.accounts.vendor_reputation;
         // Track this as a vendor deal when they start working (when marking complete)
-        // This ensures we count deals where vendor actually worked
-        vendor_rep.deals_as_vendor += 1;
-        let delivery_time = (clock.unix_timestamp - escrow.created_at) as u64;
-        vendor_rep.total_delivery_time_seconds += delivery_time;
+        // This ensures we count deals where vendor actually worked. Later tranches of a
+        // partially released milestone belong to the same deal.
+        if escrow.released_amount == 0 {
+            vendor_rep.deals_as_vendor += 1;
+            let delivery_time = (clock.unix_timestamp - escrow.created_at) as u64;
+            vendor_rep.total_delivery_time_seconds += delivery_time;
+        }
         vendor_rep.last_activity = clock.unix_timestamp;
 
         msg!("Milestone {} marked complete by {}", escrow.milestone_id, marker);
@@ -312,16 +850,17 @@ pub mod agreed_contracts {
         );
 
         let contract = &ctx.accounts.contract;
-        require!(
-            contract.participants.contains(&approver),
-            ErrorCode::NotAParticipant
-        );
+        contract.require_approver(&approver)?;
         require!(
             !escrow.approvals.contains(&approver),
             ErrorCode::AlreadyApprovedMilestone
         );
 
+        // Participants may have changed since funding: approvals from removed wallets no
+        // longer count, and everyone approving now is needed
+        escrow.approvals.retain(|wallet| contract.require_approver(wallet).is_ok());
         escrow.approvals.push(approver);
+        escrow.approvals_required = contract.approver_count() as u8;
 
         // Update approver reputation
         let approver_rep = &mut ctx.accounts.approver_reputation;
@@ -338,38 +877,62 @@ pub mod agreed_contracts {
         Ok(())
     }
 
-    pub fn release_escrow_funds(
-        ctx: Context<ReleaseEscrowFunds>,
+    /// For split milestones, pass a (destination, reputation) account pair for every
+    /// co-recipient other than `escrow_milestone.recipient`, in split order, as remaining accounts.
+    /// Released milestones stay open: ratings and warranty disputes are keyed to the escrow
+    /// account. Only cancelled and expired ones can be closed, with `close_escrow_milestone`.
+    pub fn release_escrow_funds<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseEscrowFunds<'info>>,
     ) -> Result<()> {
         let clock = Clock::get()?;
         
         // Check status and approvals first
         let (amount, milestone_id, recipient, created_at) = {
             let escrow = &ctx.accounts.escrow_milestone;
+            require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
             require!(
                 escrow.status == MilestoneStatus::MarkedComplete,
                 ErrorCode::MilestoneNotMarkedComplete
             );
             require!(
-                escrow.approvals.len() >= escrow.approvals_required as usize,
+                ctx.accounts.contract.all_approved(&escrow.approvals),
                 ErrorCode::InsufficientApprovals
             );
-            (escrow.amount, escrow.milestone_id, escrow.recipient, escrow.created_at)
+            (escrow.remaining_amount(), escrow.milestone_id, escrow.recipient, escrow.created_at)
         };
         
-        // Transfer funds from escrow PDA to recipient
-        **ctx.accounts.escrow_milestone.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.recipient.to_account_info().try_borrow_mut_lamports()? += amount;
+        // Transfer whatever earlier partial releases left in the escrow PDA (or its vault).
+        // Late penalties and a missed early-delivery bonus go back to the creator, and any
+        // retention stays in escrow until the warranty period ends.
+        let settlement = ctx.accounts.escrow_milestone.settle(amount, true);
+        let delivered = pay_recipients(
+            &ctx.accounts.escrow_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &ctx.accounts.recipient,
+            &mut ctx.accounts.vendor_reputation,
+            ctx.remaining_accounts,
+            settlement.to_recipient,
+            true,
+        )?;
+        if settlement.to_creator > 0 {
+            transfer_from_escrow(
+                &ctx.accounts.escrow_milestone,
+                EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+                &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+                &ctx.accounts.creator.key(),
+                settlement.to_creator,
+            )?;
+        }
 
         // Update status
         let escrow = &mut ctx.accounts.escrow_milestone;
         escrow.status = MilestoneStatus::Released;
-
-        // Update vendor (recipient) reputation - milestone completed
-        let vendor_rep = &mut ctx.accounts.vendor_reputation;
-        vendor_rep.completed_as_vendor += 1;
-        vendor_rep.total_value_transacted += amount;
-        vendor_rep.last_activity = clock.unix_timestamp;
+        escrow.released_amount += amount;
+        escrow.penalty_amount += settlement.penalty;
+        escrow.retained_amount += settlement.retained;
+        escrow.amount_delivered += delivered;
+        escrow.settled_at = Some(clock.unix_timestamp);
+        ctx.accounts.contract.settle_milestone(escrow);
 
         // Update client (creator) reputation - track payment time
         let client_rep = &mut ctx.accounts.client_reputation;
@@ -378,64 +941,706 @@ pub mod agreed_contracts {
         client_rep.completed_as_client += 1;
         client_rep.last_activity = clock.unix_timestamp;
 
-        msg!("Escrow milestone {} released {} lamports to {}", milestone_id, amount, recipient);
+        msg!(
+            "Escrow milestone {} released {} to {} ({} received, {} late penalty, {} bonus)",
+            milestone_id,
+            settlement.to_recipient,
+            recipient,
+            delivered,
+            settlement.penalty,
+            settlement.bonus
+        );
         Ok(())
     }
 
-    pub fn cancel_escrow_milestone(
-        ctx: Context<CancelEscrowMilestone>,
+    /// Pays out part of an approved milestone. Unless this drains the escrow, the milestone
+    /// goes back to `Funded` with its approvals cleared, so the next tranche needs a fresh
+    /// completion mark and approval round.
+    /// Split milestones take the same remaining accounts as `release_escrow_funds`.
+    pub fn release_partial_escrow_funds<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleasePartialEscrowFunds<'info>>,
+        amount: u64,
     ) -> Result<()> {
-        let creator = ctx.accounts.creator.key();
+        let clock = Clock::get()?;
 
-        // Check permissions and status first
-        let (amount, milestone_id, is_funded) = {
+        let (milestone_id, recipient, created_at, is_final) = {
             let escrow = &ctx.accounts.escrow_milestone;
+            require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
             require!(
-                escrow.creator == creator,
-                ErrorCode::OnlyCreatorCanCancelEscrow
+                escrow.creator == ctx.accounts.creator.key(),
+                ErrorCode::OnlyCreatorCanReleasePartial
             );
             require!(
-                escrow.status == MilestoneStatus::Funded || escrow.status == MilestoneStatus::Pending,
-                ErrorCode::CannotCancelMilestone
+                escrow.status == MilestoneStatus::MarkedComplete,
+                ErrorCode::MilestoneNotMarkedComplete
             );
-            (escrow.amount, escrow.milestone_id, escrow.status == MilestoneStatus::Funded)
-        };
-
+            require!(
+                ctx.accounts.contract.all_approved(&escrow.approvals),
+                ErrorCode::InsufficientApprovals
+            );
+            require!(
+                amount > 0 && amount <= escrow.remaining_amount(),
+                ErrorCode::InvalidAmount
+            );
+            (
+                escrow.milestone_id,
+                escrow.recipient,
+                escrow.created_at,
+                amount == escrow.remaining_amount(),
+            )
+        };
+
+        let settlement = ctx.accounts.escrow_milestone.settle(amount, is_final);
+        let delivered = pay_recipients(
+            &ctx.accounts.escrow_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &ctx.accounts.recipient,
+            &mut ctx.accounts.vendor_reputation,
+            ctx.remaining_accounts,
+            settlement.to_recipient,
+            is_final,
+        )?;
+        if settlement.to_creator > 0 {
+            transfer_from_escrow(
+                &ctx.accounts.escrow_milestone,
+                EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+                &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+                &ctx.accounts.creator.key(),
+                settlement.to_creator,
+            )?;
+        }
+
+        let escrow = &mut ctx.accounts.escrow_milestone;
+        escrow.released_amount += amount;
+        escrow.penalty_amount += settlement.penalty;
+        escrow.retained_amount += settlement.retained;
+        escrow.amount_delivered += delivered;
+        if is_final {
+            escrow.status = MilestoneStatus::Released;
+            escrow.settled_at = Some(clock.unix_timestamp);
+            ctx.accounts.contract.settle_milestone(escrow);
+        } else {
+            escrow.status = MilestoneStatus::Funded;
+            escrow.approvals.clear();
+            escrow.marked_complete_by = None;
+            escrow.completed_at = None;
+        }
+
+        let client_rep = &mut ctx.accounts.client_reputation;
+        client_rep.last_activity = clock.unix_timestamp;
+
+        if is_final {
+            let payment_time = (clock.unix_timestamp - created_at) as u64;
+            client_rep.total_payment_time_seconds += payment_time;
+            client_rep.completed_as_client += 1;
+        }
+
+        msg!(
+            "Escrow milestone {} released {} of {} to {} ({} received, {} late penalty, {} bonus)",
+            milestone_id,
+            settlement.to_recipient,
+            escrow.amount,
+            recipient,
+            delivered,
+            settlement.penalty,
+            settlement.bonus
+        );
+        Ok(())
+    }
+
+    /// Refunds what is left of an unsettled milestone to the creator and marks it
+    /// `Cancelled`. The account stays until `close_escrow_milestone`.
+    pub fn cancel_escrow_milestone(
+        ctx: Context<CancelEscrowMilestone>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let creator = ctx.accounts.creator.key();
+
+        // Check permissions and status first
+        let (amount, milestone_id, is_funded) = {
+            let escrow = &ctx.accounts.escrow_milestone;
+            require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
+            require!(
+                escrow.creator == creator,
+                ErrorCode::OnlyCreatorCanCancelEscrow
+            );
+            require!(
+                escrow.status == MilestoneStatus::Funded || escrow.status == MilestoneStatus::Pending,
+                ErrorCode::CannotCancelMilestone
+            );
+            // Retention from earlier tranches belongs to the recipients once the warranty ends;
+            // cancelling would sweep it to the creator along with the escrow's rent
+            require!(escrow.retained_amount == 0, ErrorCode::RetentionStillHeld);
+            (escrow.remaining_amount() + escrow.bonus_amount(), escrow.milestone_id, escrow.status == MilestoneStatus::Funded)
+        };
+
         // Refund to creator if funded
         if is_funded {
-            **ctx.accounts.escrow_milestone.to_account_info().try_borrow_mut_lamports()? -= amount;
-            **ctx.accounts.creator.to_account_info().try_borrow_mut_lamports()? += amount;
+            transfer_from_escrow(
+                &ctx.accounts.escrow_milestone,
+                EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+                &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+                &creator,
+                amount,
+            )?;
         }
 
-        // Update status
         let escrow = &mut ctx.accounts.escrow_milestone;
         escrow.status = MilestoneStatus::Cancelled;
+        escrow.settled_at = Some(clock.unix_timestamp);
 
         // Update reputation for cancellations
         let creator_rep = &mut ctx.accounts.creator_reputation;
         creator_rep.cancelled_as_client += 1;
-        creator_rep.last_activity = Clock::get()?.unix_timestamp;
+        creator_rep.last_activity = clock.unix_timestamp;
+        ctx.accounts.contract.settle_milestone(&ctx.accounts.escrow_milestone);
 
         msg!("Escrow milestone {} cancelled and refunded", milestone_id);
         Ok(())
     }
 
+    /// Permissionless: once a funded milestone is past its deadline without being marked
+    /// complete, anyone can refund what is left to the creator, record the miss and mark the
+    /// milestone `Expired`. Milestones that have paid out a tranche are not counted as
+    /// missed; their creator winds them down with `cancel_escrow_milestone`.
+    pub fn expire_milestone(
+        ctx: Context<ExpireMilestone>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let (amount, milestone_id, creator) = {
+            let escrow = &ctx.accounts.escrow_milestone;
+            require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
+            require!(
+                escrow.status == MilestoneStatus::Funded,
+                ErrorCode::MilestoneNotFunded
+            );
+            require!(escrow.released_amount == 0, ErrorCode::MilestonePartiallyReleased);
+            require!(
+                clock.unix_timestamp > escrow.deadline,
+                ErrorCode::DeadlineNotReached
+            );
+            (escrow.remaining_amount() + escrow.bonus_amount(), escrow.milestone_id, escrow.creator)
+        };
+
+        transfer_from_escrow(
+            &ctx.accounts.escrow_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+            &creator,
+            amount,
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow_milestone;
+        escrow.status = MilestoneStatus::Expired;
+        escrow.settled_at = Some(clock.unix_timestamp);
+
+        let vendor_rep = &mut ctx.accounts.vendor_reputation;
+        vendor_rep.missed_deadlines_vendor += 1;
+        vendor_rep.last_activity = clock.unix_timestamp;
+        ctx.accounts.contract.settle_milestone(&ctx.accounts.escrow_milestone);
+
+        msg!("Escrow milestone {} expired, {} refunded to {}", milestone_id, amount, creator);
+        Ok(())
+    }
+
+    /// Closes a cancelled or expired milestone, and its drained vault, returning the rent to
+    /// the creator.
+    pub fn close_escrow_milestone(
+        ctx: Context<CloseEscrowMilestone>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow_milestone;
+        require!(
+            matches!(escrow.status, MilestoneStatus::Cancelled | MilestoneStatus::Expired),
+            ErrorCode::MilestoneNotClosable
+        );
+
+        close_escrow_vault(
+            escrow,
+            EscrowTokens::new(&ctx.accounts.vault, &None, &ctx.accounts.token_program),
+            &ctx.accounts.creator.to_account_info(),
+        )?;
+
+        msg!("Escrow milestone {} closed", escrow.milestone_id);
+        Ok(())
+    }
+
+    /// Permissionless: pays the held-back retention to the recipients once the warranty
+    /// period after settlement has passed, provided no dispute is open on the milestone.
+    /// Split milestones take the same remaining accounts as `release_escrow_funds`.
+    pub fn release_retention<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseRetention<'info>>,
+    ) -> Result<()> {
+        let (amount, milestone_id, recipient) = {
+            let escrow = &ctx.accounts.escrow_milestone;
+            require!(escrow.retained_amount > 0, ErrorCode::NoRetentionHeld);
+            require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
+            let warranty_ends_at = escrow.warranty_ends_at().ok_or(ErrorCode::MilestoneNotSettled)?;
+            require!(
+                Clock::get()?.unix_timestamp >= warranty_ends_at,
+                ErrorCode::WarrantyPeriodActive
+            );
+            (escrow.retained_amount, escrow.milestone_id, escrow.recipient)
+        };
+
+        let delivered = pay_recipients(
+            &ctx.accounts.escrow_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &ctx.accounts.recipient,
+            &mut ctx.accounts.vendor_reputation,
+            ctx.remaining_accounts,
+            amount,
+            false,
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow_milestone;
+        escrow.retained_amount = 0;
+        escrow.amount_delivered += delivered;
+
+        msg!("Escrow milestone {} released {} retention to {} ({} received)", milestone_id, amount, recipient, delivered);
+        Ok(())
+    }
+
+    // ========== MILESTONE AMENDMENT FUNCTIONS ==========
+
+    /// Proposes new terms for a funded milestone. Fields left as `None` keep their current
+    /// value. The proposer's approval is counted straight away.
+    pub fn propose_milestone_amendment(
+        ctx: Context<ProposeMilestoneAmendment>,
+        new_amount: Option<u64>,
+        new_deadline: Option<i64>,
+        new_description: Option<String>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow_milestone;
+        let proposer = ctx.accounts.proposer.key();
+        let clock = Clock::get()?;
+
+        require!(
+            escrow.status == MilestoneStatus::Funded,
+            ErrorCode::MilestoneNotFunded
+        );
+        ctx.accounts.contract.require_approver(&proposer)?;
+        require!(
+            new_amount.is_some() || new_deadline.is_some() || new_description.is_some(),
+            ErrorCode::EmptyAmendment
+        );
+        if let Some(new_amount) = new_amount {
+            require!(
+                new_amount > escrow.released_amount && new_amount != escrow.amount,
+                ErrorCode::InvalidAmount
+            );
+        }
+        if let Some(new_deadline) = new_deadline {
+            require!(new_deadline > clock.unix_timestamp, ErrorCode::InvalidDeadline);
+        }
+        if let Some(new_description) = &new_description {
+            require!(new_description.len() <= 200, ErrorCode::DescriptionTooLong);
+        }
+
+        let amendment = &mut ctx.accounts.amendment;
+        amendment.escrow_milestone = escrow.key();
+        amendment.proposer = proposer;
+        amendment.new_amount = new_amount;
+        amendment.new_deadline = new_deadline;
+        amendment.new_description = new_description;
+        amendment.approvals_required = ctx.accounts.contract.approver_count() as u8;
+        amendment.approvals = vec![proposer];
+        amendment.created_at = clock.unix_timestamp;
+        amendment.bump = ctx.bumps.amendment;
+
+        msg!("Amendment to milestone {} proposed by {}", escrow.milestone_id, proposer);
+        Ok(())
+    }
+
+    pub fn approve_milestone_amendment(
+        ctx: Context<ApproveMilestoneAmendment>,
+    ) -> Result<()> {
+        let amendment = &mut ctx.accounts.amendment;
+        let approver = ctx.accounts.approver.key();
+        let contract = &ctx.accounts.contract;
+
+        contract.require_approver(&approver)?;
+        require!(
+            !amendment.approvals.contains(&approver),
+            ErrorCode::AlreadyApprovedAmendment
+        );
+
+        // Same as milestone release approvals: only current approvers count
+        amendment.approvals.retain(|wallet| contract.require_approver(wallet).is_ok());
+        amendment.approvals.push(approver);
+        amendment.approvals_required = contract.approver_count() as u8;
+
+        msg!(
+            "Amendment to milestone {} approved by {} ({}/{})",
+            ctx.accounts.escrow_milestone.milestone_id,
+            approver,
+            amendment.approvals.len(),
+            amendment.approvals_required
+        );
+        Ok(())
+    }
+
+    /// Applies a fully approved amendment. The creator signs because an increase is
+    /// deposited from their wallet (or token account) and a decrease is refunded to it.
+    pub fn apply_milestone_amendment(
+        ctx: Context<ApplyMilestoneAmendment>,
+    ) -> Result<()> {
+        let creator = ctx.accounts.creator.key();
+        let (new_amount, new_deadline, new_description) = {
+            let escrow = &ctx.accounts.escrow_milestone;
+            require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
+            let amendment = &ctx.accounts.amendment;
+            require!(
+                escrow.creator == creator,
+                ErrorCode::OnlyCreatorCanApplyAmendment
+            );
+            require!(
+                escrow.status == MilestoneStatus::Funded,
+                ErrorCode::MilestoneNotFunded
+            );
+            // A proposal left over from a closed escrow at the same address doesn't carry over
+            require!(
+                amendment.created_at >= escrow.created_at,
+                ErrorCode::StaleMilestoneAmendment
+            );
+            require!(
+                ctx.accounts.contract.all_approved(&amendment.approvals),
+                ErrorCode::InsufficientApprovals
+            );
+            if let Some(new_amount) = amendment.new_amount {
+                require!(new_amount > escrow.released_amount, ErrorCode::InvalidAmount);
+            }
+            (amendment.new_amount, amendment.new_deadline, amendment.new_description.clone())
+        };
+
+        let current_amount = ctx.accounts.escrow_milestone.amount;
+        let mint = ctx.accounts.escrow_milestone.mint;
+        match new_amount {
+            Some(new_amount) if new_amount > current_amount => {
+                let increase = new_amount - current_amount;
+                let received = match mint {
+                    None => {
+                        let cpi_context = CpiContext::new(
+                            ctx.accounts.system_program.to_account_info(),
+                            system_program::Transfer {
+                                from: ctx.accounts.creator.to_account_info(),
+                                to: ctx.accounts.escrow_milestone.to_account_info(),
+                            },
+                        );
+                        system_program::transfer(cpi_context, increase)?;
+                        ctx.accounts.creator_reputation.total_value_escrowed += increase;
+                        increase
+                    }
+                    Some(escrow_mint) => {
+                        let (Some(vault), Some(mint_account), Some(creator_token_account), Some(token_program)) = (
+                            &ctx.accounts.vault,
+                            &ctx.accounts.mint,
+                            &ctx.accounts.creator_token_account,
+                            &ctx.accounts.token_program,
+                        ) else {
+                            return err!(ErrorCode::MissingTokenAccounts);
+                        };
+                        require_keys_eq!(mint_account.key(), escrow_mint, ErrorCode::InvalidMint);
+                        deposit_tokens(
+                            token_program,
+                            creator_token_account,
+                            mint_account,
+                            vault,
+                            &ctx.accounts.creator,
+                            increase,
+                        )?
+                    }
+                };
+                ctx.accounts.escrow_milestone.amount += received;
+                ctx.accounts.creator_reputation.record_value_transacted(mint, received);
+            }
+            Some(new_amount) if new_amount < current_amount => {
+                transfer_from_escrow(
+                    &ctx.accounts.escrow_milestone,
+                    EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+                    &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+                    &creator,
+                    current_amount - new_amount,
+                )?;
+                ctx.accounts.escrow_milestone.amount = new_amount;
+            }
+            _ => {}
+        }
+
+        let escrow = &mut ctx.accounts.escrow_milestone;
+        if let Some(new_deadline) = new_deadline {
+            escrow.deadline = new_deadline;
+        }
+        if let Some(new_description) = new_description {
+            escrow.description = new_description;
+        }
+        escrow.amendment_count += 1;
+
+        ctx.accounts.creator_reputation.last_activity = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Amendment {} applied to milestone {} (amount {}, deadline {})",
+            escrow.amendment_count,
+            escrow.milestone_id,
+            escrow.amount,
+            escrow.deadline
+        );
+        Ok(())
+    }
+
+    /// Lets the proposer drop a pending amendment, e.g. one that can no longer be applied.
+    pub fn withdraw_milestone_amendment(
+        ctx: Context<WithdrawMilestoneAmendment>,
+    ) -> Result<()> {
+        msg!(
+            "Amendment to milestone {} withdrawn by {}",
+            ctx.accounts.escrow_milestone.key(),
+            ctx.accounts.proposer.key()
+        );
+        Ok(())
+    }
+
+    // ========== VESTING MILESTONE FUNCTIONS ==========
+
+    pub fn initialize_vesting_milestone(
+        ctx: Context<InitializeVestingMilestone>,
+        milestone_id: u64,
+        contract_id: u64,
+        description: String,
+        amount: u64,
+        recipient: Pubkey,
+        schedule: VestingSchedule,
+    ) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        validate_escrow_terms(contract, &ctx.accounts.creator.key(), &description, amount, &recipient)?;
+        schedule.validate()?;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.creator.to_account_info(),
+                to: ctx.accounts.vesting_milestone.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, amount)?;
+
+        let vesting = &mut ctx.accounts.vesting_milestone;
+        vesting.milestone_id = milestone_id;
+        vesting.contract_id = contract_id;
+        vesting.contract = contract.key();
+        vesting.description = description;
+        vesting.amount = amount;
+        vesting.claimed_amount = 0;
+        vesting.amount_delivered = 0;
+        vesting.recipient = recipient;
+        vesting.creator = ctx.accounts.creator.key();
+        vesting.schedule = schedule;
+        vesting.status = VestingStatus::Active;
+        vesting.cancelled_at = None;
+        vesting.mint = None;
+        vesting.created_at = Clock::get()?.unix_timestamp;
+        vesting.bump = ctx.bumps.vesting_milestone;
+
+        let creator_rep = &mut ctx.accounts.creator_reputation;
+        creator_rep.total_value_escrowed += amount;
+        creator_rep.record_value_transacted(None, amount);
+        creator_rep.deals_as_client += 1;
+        creator_rep.last_activity = Clock::get()?.unix_timestamp;
+        ctx.accounts.contract.milestone_count += 1;
+
+        msg!("Vesting milestone {} created and funded with {} lamports", milestone_id, amount);
+        Ok(())
+    }
+
+    pub fn initialize_token_vesting_milestone(
+        ctx: Context<InitializeTokenVestingMilestone>,
+        milestone_id: u64,
+        contract_id: u64,
+        description: String,
+        amount: u64,
+        recipient: Pubkey,
+        schedule: VestingSchedule,
+    ) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        validate_escrow_terms(contract, &ctx.accounts.creator.key(), &description, amount, &recipient)?;
+        validate_escrow_mint(&ctx.accounts.mint)?;
+        schedule.validate()?;
+
+        let amount = deposit_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.creator_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.creator,
+            amount,
+        )?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let mint = ctx.accounts.mint.key();
+        let vesting = &mut ctx.accounts.vesting_milestone;
+        vesting.milestone_id = milestone_id;
+        vesting.contract_id = contract_id;
+        vesting.contract = contract.key();
+        vesting.description = description;
+        vesting.amount = amount;
+        vesting.claimed_amount = 0;
+        vesting.amount_delivered = 0;
+        vesting.recipient = recipient;
+        vesting.creator = ctx.accounts.creator.key();
+        vesting.schedule = schedule;
+        vesting.status = VestingStatus::Active;
+        vesting.cancelled_at = None;
+        vesting.mint = Some(mint);
+        vesting.created_at = Clock::get()?.unix_timestamp;
+        vesting.bump = ctx.bumps.vesting_milestone;
+
+        let creator_rep = &mut ctx.accounts.creator_reputation;
+        creator_rep.record_value_transacted(Some(mint), amount);
+        creator_rep.deals_as_client += 1;
+        creator_rep.last_activity = Clock::get()?.unix_timestamp;
+        ctx.accounts.contract.milestone_count += 1;
+
+        msg!("Vesting milestone {} created and funded with {} tokens of mint {}", milestone_id, amount, mint);
+        Ok(())
+    }
+
+    pub fn claim_vested_funds(
+        ctx: Context<ClaimVestedFunds>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let recipient = ctx.accounts.recipient.key();
+
+        let (claimable, milestone_id, mint, first_claim) = {
+            let vesting = &ctx.accounts.vesting_milestone;
+            require!(
+                vesting.recipient == recipient,
+                ErrorCode::OnlyRecipientCanClaim
+            );
+            require!(
+                vesting.status != VestingStatus::Completed,
+                ErrorCode::VestingCompleted
+            );
+            let claimable = vesting.claimable_amount(clock.unix_timestamp);
+            require!(claimable > 0, ErrorCode::NothingToClaim);
+            (claimable, vesting.milestone_id, vesting.mint, vesting.claimed_amount == 0)
+        };
+
+        let destination = match &ctx.accounts.recipient_token_account {
+            Some(token_account) => token_account.to_account_info(),
+            None => ctx.accounts.recipient.to_account_info(),
+        };
+        let delivered = transfer_from_escrow(
+            &ctx.accounts.vesting_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &destination,
+            &recipient,
+            claimable,
+        )?;
+
+        let vesting = &mut ctx.accounts.vesting_milestone;
+        vesting.claimed_amount += claimable;
+        vesting.amount_delivered += delivered;
+        // A cancelled schedule stays cancelled once its vested part is paid out
+        if vesting.claimed_amount == vesting.amount && vesting.status == VestingStatus::Active {
+            vesting.status = VestingStatus::Completed;
+        }
+
+        let vendor_rep = &mut ctx.accounts.vendor_reputation;
+        if first_claim {
+            vendor_rep.deals_as_vendor += 1;
+        }
+        if vesting.status == VestingStatus::Completed {
+            vendor_rep.completed_as_vendor += 1;
+        }
+        vendor_rep.record_value_transacted(mint, delivered);
+        vendor_rep.last_activity = clock.unix_timestamp;
+
+        msg!("Vesting milestone {} paid {} to {} ({} received)", milestone_id, claimable, recipient, delivered);
+        Ok(())
+    }
+
+    /// Stops vesting at the current time and refunds the unvested remainder to the creator.
+    /// Whatever had already vested stays claimable by the recipient.
+    pub fn cancel_vesting_milestone(
+        ctx: Context<CancelVestingMilestone>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let creator = ctx.accounts.creator.key();
+
+        let (unvested, milestone_id) = {
+            let vesting = &ctx.accounts.vesting_milestone;
+            require!(
+                vesting.creator == creator,
+                ErrorCode::OnlyCreatorCanCancelEscrow
+            );
+            require!(
+                vesting.status == VestingStatus::Active,
+                ErrorCode::CannotCancelMilestone
+            );
+            (vesting.amount - vesting.vested_amount(clock.unix_timestamp), vesting.milestone_id)
+        };
+        // Once the schedule has fully vested there is nothing left to stop
+        require!(unvested > 0, ErrorCode::CannotCancelMilestone);
+
+        transfer_from_escrow(
+            &ctx.accounts.vesting_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+            &creator,
+            unvested,
+        )?;
+
+        let vesting = &mut ctx.accounts.vesting_milestone;
+        vesting.status = VestingStatus::Cancelled;
+        vesting.amount -= unvested;
+        vesting.cancelled_at = Some(clock.unix_timestamp);
+
+        let creator_rep = &mut ctx.accounts.creator_reputation;
+        creator_rep.cancelled_as_client += 1;
+        creator_rep.last_activity = clock.unix_timestamp;
+
+        msg!("Vesting milestone {} cancelled, {} refunded", milestone_id, unvested);
+        Ok(())
+    }
+
+    /// The milestone's creator rates one of its recipients once the milestone is released.
     pub fn rate_vendor(
         ctx: Context<RateVendor>,
         rating: u8,
     ) -> Result<()> {
         require!(
-            rating >= 1 && rating <= 5,
+            (1..=5).contains(&rating),
             ErrorCode::InvalidRating
         );
 
+        let escrow = &ctx.accounts.escrow_milestone;
+        let rater = ctx.accounts.rater.key();
+        let vendor = ctx.accounts.vendor_reputation.wallet;
+        require!(
+            escrow.status == MilestoneStatus::Released,
+            ErrorCode::MilestoneNotReleased
+        );
+        require_keys_neq!(rater, vendor, ErrorCode::CannotRateSelf);
+        require!(
+            escrow.creator == rater && escrow.is_recipient(&vendor),
+            ErrorCode::NotACounterparty
+        );
+        ctx.accounts.rating_receipt.set_inner(RatingReceipt {
+            escrow_milestone: escrow.key(),
+            rater,
+            ratee: vendor,
+            rating,
+            created_at: Clock::get()?.unix_timestamp,
+            bump: ctx.bumps.rating_receipt,
+        });
+
         let vendor_rep = &mut ctx.accounts.vendor_reputation;
         vendor_rep.quality_ratings_sum += rating as u32;
         vendor_rep.quality_ratings_count += 1;
         
         // Calculate average vendor score (0-500 scale, where 500 = 5.0)
-        if vendor_rep.quality_ratings_count > 0 {
-            vendor_rep.vendor_score = (vendor_rep.quality_ratings_sum * 100) / vendor_rep.quality_ratings_count;
+        if let Some(score) = (vendor_rep.quality_ratings_sum * 100).checked_div(vendor_rep.quality_ratings_count) {
+            vendor_rep.vendor_score = score;
         }
         
         vendor_rep.last_activity = Clock::get()?.unix_timestamp;
@@ -444,22 +1649,44 @@ pub mod agreed_contracts {
         Ok(())
     }
 
+    /// A recipient of a released milestone rates the milestone's creator.
     pub fn rate_client(
         ctx: Context<RateClient>,
         rating: u8,
     ) -> Result<()> {
         require!(
-            rating >= 1 && rating <= 5,
+            (1..=5).contains(&rating),
             ErrorCode::InvalidRating
         );
 
+        let escrow = &ctx.accounts.escrow_milestone;
+        let rater = ctx.accounts.rater.key();
+        let client = ctx.accounts.client_reputation.wallet;
+        require!(
+            escrow.status == MilestoneStatus::Released,
+            ErrorCode::MilestoneNotReleased
+        );
+        require_keys_neq!(rater, client, ErrorCode::CannotRateSelf);
+        require!(
+            escrow.creator == client && escrow.is_recipient(&rater),
+            ErrorCode::NotACounterparty
+        );
+        ctx.accounts.rating_receipt.set_inner(RatingReceipt {
+            escrow_milestone: escrow.key(),
+            rater,
+            ratee: client,
+            rating,
+            created_at: Clock::get()?.unix_timestamp,
+            bump: ctx.bumps.rating_receipt,
+        });
+
         let client_rep = &mut ctx.accounts.client_reputation;
         client_rep.responsiveness_ratings_sum += rating as u32;
         client_rep.responsiveness_ratings_count += 1;
         
         // Calculate average client score (0-500 scale, where 500 = 5.0)
-        if client_rep.responsiveness_ratings_count > 0 {
-            client_rep.client_score = (client_rep.responsiveness_ratings_sum * 100) / client_rep.responsiveness_ratings_count;
+        if let Some(score) = (client_rep.responsiveness_ratings_sum * 100).checked_div(client_rep.responsiveness_ratings_count) {
+            client_rep.client_score = score;
         }
         
         client_rep.last_activity = Clock::get()?.unix_timestamp;
@@ -468,332 +1695,2973 @@ pub mod agreed_contracts {
         Ok(())
     }
 
-    pub fn report_dispute(
-        ctx: Context<ReportDispute>,
-        is_vendor_dispute: bool,
+    // ========== ARBITER REGISTRY FUNCTIONS ==========
+
+    /// Creates the program-wide arbiter registry. Only the program's upgrade authority can
+    /// initialize it, and becomes its authority. Arbiters stake `stake_mint` tokens, or SOL
+    /// when it is `None`. Disputes start out on the default `DisputeTimings`.
+    pub fn initialize_arbiter_registry(
+        ctx: Context<InitializeArbiterRegistry>,
+        appeal_fee: u64,
+        min_stake: u64,
+        stake_mint: Option<Pubkey>,
     ) -> Result<()> {
-        let reputation = &mut ctx.accounts.reputation;
-        
-        if is_vendor_dispute {
-            reputation.dispute_count_vendor += 1;
-            msg!("Dispute reported for vendor: {}", reputation.wallet);
-        } else {
-            reputation.dispute_count_client += 1;
-            msg!("Dispute reported for client: {}", reputation.wallet);
-        }
-        
-        reputation.last_activity = Clock::get()?.unix_timestamp;
+        let registry = &mut ctx.accounts.registry;
+        registry.authority = ctx.accounts.authority.key();
+        registry.appeal_fee = appeal_fee;
+        registry.bump = ctx.bumps.registry;
+        registry.min_stake = min_stake;
+        registry.stake_mint = stake_mint;
+        registry.dispute_timings = DisputeTimings::DEFAULT;
+
+        msg!("Arbiter registry created by {}", registry.authority);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct InitializeReputation<'info> {
-    #[account(
+    /// New `dispute_timings` apply to disputes opened (or appealed) afterwards.
+    pub fn update_arbiter_registry(
+        ctx: Context<UpdateArbiterRegistry>,
+        appeal_fee: u64,
+        min_stake: u64,
+        dispute_timings: DisputeTimings,
+    ) -> Result<()> {
+        dispute_timings.validate()?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.appeal_fee = appeal_fee;
+        registry.min_stake = min_stake;
+        registry.dispute_timings = dispute_timings;
+        msg!("Arbiter registry appeal fee set to {}, minimum stake to {}", appeal_fee, min_stake);
+        Ok(())
+    }
+
+    // ========== ARBITER FUNCTIONS ==========
+
+    pub fn register_arbiter(
+        ctx: Context<RegisterArbiter>,
+        fee: u64,
+        stake: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.registry.stake_mint.is_none(),
+            ErrorCode::MissingTokenAccounts
+        );
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.wallet.to_account_info(),
+                to: ctx.accounts.arbiter.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, stake)?;
+
+        let wallet = ctx.accounts.wallet.key();
+        ctx.accounts.arbiter.set_inner(Arbiter::new(wallet, None, fee, stake, ctx.bumps.arbiter)?);
+
+        msg!("Arbiter {} registered with {} lamports staked", wallet, stake);
+        Ok(())
+    }
+
+    pub fn register_token_arbiter(
+        ctx: Context<RegisterTokenArbiter>,
+        fee: u64,
+        stake: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.registry.stake_mint == Some(ctx.accounts.mint.key()),
+            ErrorCode::InvalidMint
+        );
+
+        let stake = deposit_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.wallet_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.wallet,
+            stake,
+        )?;
+
+        let wallet = ctx.accounts.wallet.key();
+        let mint = ctx.accounts.mint.key();
+        ctx.accounts.arbiter.set_inner(Arbiter::new(wallet, Some(mint), fee, stake, ctx.bumps.arbiter)?);
+
+        msg!("Arbiter {} registered with {} tokens of mint {} staked", wallet, stake, mint);
+        Ok(())
+    }
+
+    pub fn add_arbiter_stake(
+        ctx: Context<AddArbiterStake>,
+        amount: u64,
+    ) -> Result<()> {
+        let received = match ctx.accounts.arbiter.stake_mint {
+            None => {
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.wallet.to_account_info(),
+                        to: ctx.accounts.arbiter.to_account_info(),
+                    },
+                );
+                system_program::transfer(cpi_context, amount)?;
+                amount
+            }
+            Some(stake_mint) => {
+                let (Some(vault), Some(mint), Some(wallet_token_account), Some(token_program)) = (
+                    &ctx.accounts.vault,
+                    &ctx.accounts.mint,
+                    &ctx.accounts.wallet_token_account,
+                    &ctx.accounts.token_program,
+                ) else {
+                    return err!(ErrorCode::MissingTokenAccounts);
+                };
+                require_keys_eq!(mint.key(), stake_mint, ErrorCode::InvalidMint);
+                deposit_tokens(token_program, wallet_token_account, mint, vault, &ctx.accounts.wallet, amount)?
+            }
+        };
+
+        let arbiter = &mut ctx.accounts.arbiter;
+        arbiter.stake += received;
+
+        msg!("Arbiter {} stake increased to {}", arbiter.wallet, arbiter.stake);
+        Ok(())
+    }
+
+    /// Sets the arbiter's per-vote fee and whether it takes new cases. Going inactive starts
+    /// the unbonding period after which stake can be withdrawn.
+    pub fn update_arbiter(
+        ctx: Context<UpdateArbiter>,
+        fee: u64,
+        active: bool,
+    ) -> Result<()> {
+        let arbiter = &mut ctx.accounts.arbiter;
+        if arbiter.active && !active {
+            arbiter.deactivated_at = Some(Clock::get()?.unix_timestamp);
+        } else if active {
+            arbiter.deactivated_at = None;
+        }
+        arbiter.fee = fee;
+        arbiter.active = active;
+
+        msg!("Arbiter {} fee set to {}, active: {}", arbiter.wallet, fee, active);
+        Ok(())
+    }
+
+    pub fn withdraw_arbiter_stake(
+        ctx: Context<WithdrawArbiterStake>,
+        amount: u64,
+    ) -> Result<()> {
+        let wallet = ctx.accounts.wallet.key();
+        {
+            let arbiter = &ctx.accounts.arbiter;
+            let deactivated_at = arbiter.deactivated_at.ok_or(ErrorCode::ArbiterStillActive)?;
+            require!(
+                Clock::get()?.unix_timestamp >= deactivated_at + Arbiter::UNBONDING_SECONDS,
+                ErrorCode::StakeUnbonding
+            );
+            require!(amount > 0 && amount <= arbiter.stake, ErrorCode::InvalidAmount);
+        }
+
+        transfer_from_escrow(
+            &ctx.accounts.arbiter,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &refund_destination(&ctx.accounts.wallet, ctx.accounts.wallet_token_account.as_ref()),
+            &wallet,
+            amount,
+        )?;
+
+        let arbiter = &mut ctx.accounts.arbiter;
+        arbiter.stake -= amount;
+
+        msg!("Arbiter {} withdrew {} of stake", wallet, amount);
+        Ok(())
+    }
+
+    /// Registry authority: takes `amount` of an arbiter's stake for misconduct.
+    pub fn slash_arbiter(
+        ctx: Context<SlashArbiter>,
+        amount: u64,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        require!(
+            amount > 0 && amount <= ctx.accounts.arbiter.stake,
+            ErrorCode::InvalidAmount
+        );
+
+        transfer_from_escrow(
+            &ctx.accounts.arbiter,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &refund_destination(&ctx.accounts.authority, ctx.accounts.authority_token_account.as_ref()),
+            &authority,
+            amount,
+        )?;
+
+        let arbiter = &mut ctx.accounts.arbiter;
+        arbiter.stake -= amount;
+        arbiter.slashed_amount += amount;
+
+        msg!("Arbiter {} slashed by {}", arbiter.wallet, amount);
+        Ok(())
+    }
+
+    /// Permissionless: once an appeal changes a ruling, counts it against each first-round
+    /// arbiter who voted for the ruling that was overturned.
+    pub fn record_overturned_ruling(
+        ctx: Context<RecordOverturnedRuling>,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        let arbiter = &mut ctx.accounts.arbiter;
+        require!(
+            dispute.status == DisputeStatus::Resolved && dispute.round > 0,
+            ErrorCode::DisputeNotRuled
+        );
+        let first_bps = dispute.first_ruling_bps.ok_or(ErrorCode::DisputeNotRuled)?;
+        require!(
+            dispute.recipient_bps != Some(first_bps),
+            ErrorCode::RulingNotOverturned
+        );
+        let index = dispute
+            .first_round_votes
+            .iter()
+            .position(|vote| vote.arbiter == arbiter.wallet && vote.recipient_bps == first_bps)
+            .ok_or(ErrorCode::RulingNotOverturned)?;
+        dispute.first_round_votes.remove(index);
+        arbiter.rulings_overturned += 1;
+
+        msg!("Ruling by arbiter {} on dispute {} recorded as overturned", arbiter.wallet, dispute.key());
+        Ok(())
+    }
+
+    // ========== DISPUTE FUNCTIONS ==========
+
+    /// Opens a dispute over a milestone's escrowed funds, freezing release, cancellation
+    /// and expiry until a ruling is executed. Either side of the milestone can open one
+    /// while funds are held, including retention during the warranty period. The contract's
+    /// arbiter forms the initial panel if it is active and sufficiently staked (pass its
+    /// `Arbiter` account); otherwise the registry authority assigns one. A dispute that gets
+    /// no ruling by its resolution deadline can be wound down with `resolve_stalled_dispute`.
+    /// Each dispute over a milestone gets its own account, so a lapsed or resolved one does
+    /// not stop the milestone being disputed again.
+    pub fn open_dispute(
+        ctx: Context<OpenDispute>,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let claimant = ctx.accounts.claimant.key();
+        let timings = ctx.accounts.registry.dispute_timings.clone();
+        let panel: Vec<Pubkey> = match (&ctx.accounts.contract.arbiter, &ctx.accounts.contract_arbiter) {
+            (Some(wallet), Some(arbiter)) if arbiter.wallet == *wallet && arbiter.is_eligible(&ctx.accounts.registry) => {
+                vec![*wallet]
+            }
+            _ => Vec::new(),
+        };
+        let escrow = &mut ctx.accounts.escrow_milestone;
+
+        let respondent = if claimant == escrow.creator {
+            escrow.recipient
+        } else {
+            require!(escrow.is_recipient(&claimant), ErrorCode::OnlyPartiesCanDispute);
+            escrow.creator
+        };
+        require!(!escrow.disputed, ErrorCode::MilestoneDisputed);
+        match escrow.status {
+            MilestoneStatus::Funded | MilestoneStatus::MarkedComplete => {}
+            MilestoneStatus::Released => {
+                require!(escrow.retained_amount > 0, ErrorCode::NoFundsInEscrow);
+                let warranty_ends_at = escrow.warranty_ends_at().ok_or(ErrorCode::MilestoneNotSettled)?;
+                require!(
+                    clock.unix_timestamp < warranty_ends_at,
+                    ErrorCode::WarrantyPeriodEnded
+                );
+            }
+            _ => return err!(ErrorCode::NoFundsInEscrow),
+        }
+
+        escrow.disputed = true;
+        let index = escrow.dispute_count;
+        escrow.dispute_count += 1;
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.escrow_milestone = escrow.key();
+        dispute.claimant = claimant;
+        dispute.respondent = respondent;
+        dispute.evidence_hash = evidence_hash;
+        dispute.status = DisputeStatus::Open;
+        dispute.recipient_bps = None;
+        dispute.opened_at = clock.unix_timestamp;
+        dispute.resolved_at = None;
+        dispute.bump = ctx.bumps.dispute;
+        dispute.evidence_deadline = clock.unix_timestamp + timings.evidence_seconds;
+        dispute.panel = panel;
+        dispute.votes = Vec::new();
+        dispute.round = 0;
+        dispute.ruled_at = None;
+        dispute.first_ruling_bps = None;
+        dispute.appellant = None;
+        dispute.appeal_fee = 0;
+        dispute.fee_pool = 0;
+        dispute.first_round_votes = Vec::new();
+        dispute.resolution_deadline = clock.unix_timestamp + timings.resolution_seconds;
+        dispute.appeal_window_seconds = timings.appeal_window_seconds;
+        dispute.index = index;
+
+        let evidence_log = &mut ctx.accounts.evidence_log;
+        evidence_log.dispute = dispute.key();
+        evidence_log.entries = Vec::new();
+        evidence_log.bump = ctx.bumps.evidence_log;
+
+        msg!("Dispute opened on milestone {} by {} against {}", escrow.milestone_id, claimant, respondent);
+        Ok(())
+    }
+
+    /// Appends a piece of evidence to the dispute's log. Only the claimant and respondent
+    /// can submit, and only until the evidence deadline.
+    pub fn submit_evidence(
+        ctx: Context<SubmitEvidence>,
+        document: DocumentRef,
+        label: String,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let dispute = &ctx.accounts.dispute;
+        let submitter = ctx.accounts.submitter.key();
+
+        require!(
+            dispute.status == DisputeStatus::Open,
+            ErrorCode::DisputeNotOpen
+        );
+        require!(
+            submitter == dispute.claimant || submitter == dispute.respondent,
+            ErrorCode::NotADisputeParty
+        );
+        require!(
+            clock.unix_timestamp <= dispute.evidence_deadline,
+            ErrorCode::EvidencePeriodEnded
+        );
+        require!(label.len() <= EvidenceEntry::MAX_LABEL_LEN, ErrorCode::EvidenceLabelTooLong);
+        document.validate()?;
+
+        let evidence_log = &mut ctx.accounts.evidence_log;
+        require!(
+            evidence_log.entries.len() < EvidenceLog::MAX_ENTRIES,
+            ErrorCode::EvidenceLogFull
+        );
+        evidence_log.entries.push(EvidenceEntry {
+            submitter,
+            document,
+            label,
+            submitted_at: clock.unix_timestamp,
+        });
+
+        msg!(
+            "Evidence {} submitted to dispute {} by {}",
+            evidence_log.entries.len(),
+            dispute.key(),
+            submitter
+        );
+        Ok(())
+    }
+
+    /// Registry authority: seats a panel of active, sufficiently staked arbiters on a dispute,
+    /// either before the first vote or, after an appeal, a panel larger than the one that
+    /// ruled. Pass each member's `Arbiter` account as remaining accounts, in panel order.
+    pub fn assign_dispute_panel<'info>(
+        ctx: Context<'_, '_, '_, 'info, AssignDisputePanel<'info>>,
+        panel: Vec<Pubkey>,
+    ) -> Result<()> {
+        let registry = &ctx.accounts.registry;
+        let escrow = &ctx.accounts.escrow_milestone;
+        let dispute = &mut ctx.accounts.dispute;
+
+        match dispute.status {
+            DisputeStatus::Open => require!(dispute.votes.is_empty(), ErrorCode::PanelAlreadyVoting),
+            DisputeStatus::Appealed => require!(
+                panel.len() > dispute.panel.len(),
+                ErrorCode::AppealPanelTooSmall
+            ),
+            _ => return err!(ErrorCode::DisputeNotOpen),
+        }
+        require!(
+            panel.len() % 2 == 1 && panel.len() <= Dispute::MAX_PANEL,
+            ErrorCode::InvalidPanel
+        );
+        require!(
+            ctx.remaining_accounts.len() == panel.len(),
+            ErrorCode::ArbiterNotEligible
+        );
+        for (i, (arbiter, arbiter_info)) in panel.iter().zip(ctx.remaining_accounts).enumerate() {
+            require_keys_eq!(*arbiter_info.owner, crate::ID, ErrorCode::ArbiterNotEligible);
+            let arbiter_account = Arbiter::try_deserialize(&mut &arbiter_info.try_borrow_data()?[..])?;
+            require!(
+                arbiter_account.wallet == *arbiter && arbiter_account.is_eligible(registry),
+                ErrorCode::ArbiterNotEligible
+            );
+            require!(
+                *arbiter != escrow.creator && !escrow.is_recipient(arbiter) && !panel[..i].contains(arbiter),
+                ErrorCode::InvalidPanel
+            );
+        }
+
+        dispute.panel = panel;
+        dispute.votes = Vec::new();
+        dispute.status = DisputeStatus::Open;
+
+        msg!("Panel of {} assigned to dispute {} (round {})", dispute.panel.len(), dispute.key(), dispute.round);
+        Ok(())
+    }
+
+    /// Tops up the lamports that pay panel arbiters' fees as they vote. Whatever is left
+    /// when the ruling executes goes back to the claimant.
+    pub fn fund_dispute_fees(
+        ctx: Context<FundDisputeFees>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(
+            !matches!(ctx.accounts.dispute.status, DisputeStatus::Resolved | DisputeStatus::Lapsed),
+            ErrorCode::DisputeNotOpen
+        );
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.dispute.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, amount)?;
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.fee_pool += amount;
+
+        msg!("Dispute {} fee pool topped up to {}", dispute.key(), dispute.fee_pool);
+        Ok(())
+    }
+
+    /// A panel member votes for the share of the held funds, in basis points, that should go
+    /// to the recipients. A strict majority on one share decides the ruling; if every member
+    /// has voted without one, the median vote does. The arbiter's fee is paid from the
+    /// dispute's fee pool when it votes. Every arbiter whose vote matches the ruling is
+    /// credited with issuing it, so the deciding vote passes the `Arbiter` accounts of the
+    /// earlier voters who chose that share, in vote order, as remaining accounts.
+    pub fn vote_on_dispute<'info>(
+        ctx: Context<'_, '_, '_, 'info, VoteOnDispute<'info>>,
+        recipient_bps: u16,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let dispute = &mut ctx.accounts.dispute;
+        let arbiter = ctx.accounts.arbiter.key();
+
+        require!(
+            recipient_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidRuling
+        );
+        require!(
+            dispute.status == DisputeStatus::Open,
+            ErrorCode::DisputeNotOpen
+        );
+        require!(
+            clock.unix_timestamp > dispute.evidence_deadline,
+            ErrorCode::EvidencePeriodActive
+        );
+        require!(dispute.panel.contains(&arbiter), ErrorCode::NotOnPanel);
+        require!(
+            !dispute.votes.iter().any(|vote| vote.arbiter == arbiter),
+            ErrorCode::AlreadyVoted
+        );
+        // Stake can be withdrawn after seating, so it is checked again at the vote
+        require!(
+            ctx.accounts.arbiter_account.is_eligible(&ctx.accounts.registry),
+            ErrorCode::ArbiterNotEligible
+        );
+
+        let fee = ctx.accounts.arbiter_account.fee;
+        require!(dispute.fee_pool >= fee, ErrorCode::InsufficientDisputeFees);
+        dispute.fee_pool -= fee;
+        **dispute.to_account_info().try_borrow_mut_lamports()? -= fee;
+        **ctx.accounts.arbiter.to_account_info().try_borrow_mut_lamports()? += fee;
+
+        dispute.votes.push(PanelVote { arbiter, recipient_bps });
+        msg!("Dispute {} vote by {} ({}/{})", dispute.key(), arbiter, dispute.votes.len(), dispute.panel.len());
+
+        if let Some(ruling) = dispute.tally() {
+            if recipient_bps == ruling {
+                ctx.accounts.arbiter_account.rulings_issued += 1;
+            }
+            let mut arbiter_accounts = ctx.remaining_accounts.iter();
+            for vote in dispute.votes.iter().filter(|vote| vote.arbiter != arbiter && vote.recipient_bps == ruling) {
+                let Some(arbiter_info) = arbiter_accounts.next() else {
+                    return err!(ErrorCode::InvalidVoterArbiter);
+                };
+                require_keys_eq!(*arbiter_info.owner, crate::ID, ErrorCode::InvalidVoterArbiter);
+                let mut data = arbiter_info.try_borrow_mut_data()?;
+                let mut arbiter_account = Arbiter::try_deserialize(&mut &data[..])?;
+                require_keys_eq!(arbiter_account.wallet, vote.arbiter, ErrorCode::InvalidVoterArbiter);
+                arbiter_account.rulings_issued += 1;
+                arbiter_account.try_serialize(&mut &mut data[..])?;
+            }
+
+            dispute.status = DisputeStatus::Ruled;
+            dispute.recipient_bps = Some(ruling);
+            dispute.ruled_at = Some(clock.unix_timestamp);
+            msg!("Dispute {} ruled {} bps to recipients (round {})", dispute.key(), ruling, dispute.round);
+        }
+        Ok(())
+    }
+
+    /// Either party can appeal a first-round ruling once, within the appeal window, by
+    /// posting the registry's appeal fee into the escrow milestone account.
+    pub fn appeal_ruling(
+        ctx: Context<AppealRuling>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let appellant = ctx.accounts.appellant.key();
+        let appeal_fee = ctx.accounts.registry.appeal_fee;
+        {
+            let dispute = &ctx.accounts.dispute;
+            require!(
+                dispute.status == DisputeStatus::Ruled,
+                ErrorCode::DisputeNotRuled
+            );
+            require!(dispute.round == 0, ErrorCode::AlreadyAppealed);
+            require!(
+                appellant == dispute.claimant || appellant == dispute.respondent,
+                ErrorCode::NotADisputeParty
+            );
+            require!(
+                clock.unix_timestamp <= dispute.appeal_deadline(),
+                ErrorCode::AppealWindowClosed
+            );
+        }
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.appellant.to_account_info(),
+                to: ctx.accounts.escrow_milestone.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, appeal_fee)?;
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.status = DisputeStatus::Appealed;
+        dispute.round = 1;
+        dispute.first_ruling_bps = dispute.recipient_bps;
+        dispute.first_round_votes = std::mem::take(&mut dispute.votes);
+        dispute.recipient_bps = None;
+        dispute.ruled_at = None;
+        dispute.appellant = Some(appellant);
+        dispute.appeal_fee = appeal_fee;
+        dispute.resolution_deadline = clock.unix_timestamp + ctx.accounts.registry.dispute_timings.resolution_seconds;
+
+        msg!("Dispute {} ruling appealed by {} with a {} lamport fee", dispute.key(), appellant, appeal_fee);
+        Ok(())
+    }
+
+    /// Permissionless: pays out a final ruling once the appeal window has passed (or after
+    /// the appeal round). `recipient_bps` of everything still held goes to the recipients
+    /// (per the milestone's splits), the rest back to the creator, and the party the ruling
+    /// goes against has its dispute counter bumped. Unspent arbiter fees go back to the
+    /// claimant. An appeal fee goes back to the appellant
+    /// if the appeal moved the ruling in their favour, otherwise to the other party. Split
+    /// milestones take the same remaining accounts as `release_escrow_funds`.
+    pub fn execute_ruling<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteRuling<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let recipient_bps = {
+            let dispute = &ctx.accounts.dispute;
+            require!(
+                dispute.status == DisputeStatus::Ruled,
+                ErrorCode::DisputeNotRuled
+            );
+            require!(
+                dispute.round > 0 || clock.unix_timestamp > dispute.appeal_deadline(),
+                ErrorCode::AppealWindowOpen
+            );
+            dispute.recipient_bps.ok_or(ErrorCode::DisputeNotRuled)?
+        };
+
+        let (held, creator, milestone_id) = {
+            let escrow = &ctx.accounts.escrow_milestone;
+            let held = if escrow.status == MilestoneStatus::Released {
+                escrow.retained_amount
+            } else {
+                escrow.remaining_amount() + escrow.bonus_amount() + escrow.retained_amount
+            };
+            (held, escrow.creator, escrow.milestone_id)
+        };
+        let to_recipient = (held as u128 * recipient_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        let to_creator = held - to_recipient;
+
+        let delivered = pay_recipients(
+            &ctx.accounts.escrow_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &ctx.accounts.recipient,
+            &mut ctx.accounts.vendor_reputation,
+            ctx.remaining_accounts,
+            to_recipient,
+            false,
+        )?;
+        transfer_from_escrow(
+            &ctx.accounts.escrow_milestone,
+            EscrowTokens::new(&ctx.accounts.vault, &ctx.accounts.mint, &ctx.accounts.token_program),
+            &refund_destination(&ctx.accounts.creator, ctx.accounts.creator_token_account.as_ref()),
+            &creator,
+            to_creator,
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow_milestone;
+        if escrow.status != MilestoneStatus::Released {
+            escrow.status = MilestoneStatus::Resolved;
+            escrow.released_amount = escrow.amount;
+            escrow.settled_at = Some(clock.unix_timestamp);
+            ctx.accounts.contract.settle_milestone(escrow);
+        }
+        escrow.retained_amount = 0;
+        escrow.amount_delivered += delivered;
+        escrow.disputed = false;
+
+        if let Some(fee_recipient) = ctx.accounts.dispute.appeal_fee_recipient(&creator) {
+            let appeal_fee = ctx.accounts.dispute.appeal_fee;
+            let Some(destination) = &ctx.accounts.appeal_fee_recipient else {
+                return err!(ErrorCode::InvalidDestination);
+            };
+            require_keys_eq!(destination.key(), fee_recipient, ErrorCode::InvalidDestination);
+            **ctx.accounts.escrow_milestone.to_account_info().try_borrow_mut_lamports()? -= appeal_fee;
+            **destination.try_borrow_mut_lamports()? += appeal_fee;
+        }
+
+        let dispute = &mut ctx.accounts.dispute;
+        if dispute.fee_pool > 0 {
+            **dispute.to_account_info().try_borrow_mut_lamports()? -= dispute.fee_pool;
+            **ctx.accounts.claimant.try_borrow_mut_lamports()? += dispute.fee_pool;
+            dispute.fee_pool = 0;
+        }
+        dispute.status = DisputeStatus::Resolved;
+        dispute.resolved_at = Some(clock.unix_timestamp);
+
+        // Whoever the ruling mostly goes against is counted as at fault; an even split
+        // counts against neither side
+        let recipient_share = recipient_bps as u64 * 2;
+        if recipient_share > BPS_DENOMINATOR {
+            let client_rep = &mut ctx.accounts.client_reputation;
+            client_rep.dispute_count_client += 1;
+            client_rep.last_activity = clock.unix_timestamp;
+        } else if recipient_share < BPS_DENOMINATOR {
+            ctx.accounts.vendor_reputation.dispute_count_vendor += 1;
+        }
+
+        msg!(
+            "Dispute on milestone {} executed: {} to recipients, {} to creator",
+            milestone_id,
+            to_recipient,
+            to_creator
+        );
+        Ok(())
+    }
+
+    /// Either party's fallback once a dispute has sat past its resolution deadline without
+    /// a ruling, e.g. because no panel was ever seated. An open dispute lapses: the milestone
+    /// is unfrozen as it was and the fee pool goes back to the claimant. A stalled appeal
+    /// reinstates the first ruling for `execute_ruling` and returns the appeal fee to the
+    /// appellant.
+    pub fn resolve_stalled_dispute(
+        ctx: Context<ResolveStalledDispute>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let caller = ctx.accounts.caller.key();
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            caller == dispute.claimant || caller == dispute.respondent,
+            ErrorCode::NotADisputeParty
+        );
+        require!(
+            clock.unix_timestamp > dispute.resolution_deadline,
+            ErrorCode::DisputeNotStalled
+        );
+
+        match dispute.status {
+            DisputeStatus::Open => {
+                if dispute.fee_pool > 0 {
+                    **dispute.to_account_info().try_borrow_mut_lamports()? -= dispute.fee_pool;
+                    **ctx.accounts.claimant.try_borrow_mut_lamports()? += dispute.fee_pool;
+                    dispute.fee_pool = 0;
+                }
+                dispute.status = DisputeStatus::Lapsed;
+                dispute.resolved_at = Some(clock.unix_timestamp);
+                ctx.accounts.escrow_milestone.disputed = false;
+
+                msg!("Dispute {} lapsed without a ruling", dispute.key());
+            }
+            DisputeStatus::Appealed => {
+                let appellant = dispute.appellant.ok_or(ErrorCode::DisputeNotRuled)?;
+                if dispute.appeal_fee > 0 {
+                    let Some(destination) = &ctx.accounts.appellant else {
+                        return err!(ErrorCode::InvalidDestination);
+                    };
+                    require_keys_eq!(destination.key(), appellant, ErrorCode::InvalidDestination);
+                    **ctx.accounts.escrow_milestone.to_account_info().try_borrow_mut_lamports()? -= dispute.appeal_fee;
+                    **destination.try_borrow_mut_lamports()? += dispute.appeal_fee;
+                    dispute.appeal_fee = 0;
+                }
+                dispute.status = DisputeStatus::Ruled;
+                dispute.recipient_bps = dispute.first_ruling_bps;
+                dispute.ruled_at = Some(clock.unix_timestamp);
+
+                msg!("Appeal on dispute {} stalled; first ruling reinstated", dispute.key());
+            }
+            _ => return err!(ErrorCode::DisputeNotStalled),
+        }
+        Ok(())
+    }
+}
+
+/// Moves the contract to the amendment's document, chaining the new version to a hash of
+/// the document it replaces. Cleared approvals are undone in `approver_reputations` the same
+/// way `revoke_approval` undoes one.
+fn apply_contract_amendment(
+    contract: &mut Contract,
+    amendment: &mut ContractAmendment,
+    approver_reputations: &[AccountInfo],
+) -> Result<()> {
+    require!(
+        amendment.version == contract.version + 1
+            && contract.document.as_ref() == Some(&amendment.previous_document),
+        ErrorCode::StaleAmendment
+    );
+
+    contract.previous_version_hash = amendment.previous_document.fingerprint();
+    contract.document = Some(amendment.document.clone());
+    contract.version = amendment.version;
+
+    // Approvals and rejections were given for the previous text
+    let mut reputation_accounts = approver_reputations.iter();
+    for approver in contract.approvers.iter() {
+        let Some(reputation_info) = reputation_accounts.next() else {
+            return err!(ErrorCode::InvalidApproverReputation);
+        };
+        require_keys_eq!(*reputation_info.owner, crate::ID, ErrorCode::InvalidApproverReputation);
+        let mut data = reputation_info.try_borrow_mut_data()?;
+        let mut reputation = UserReputation::try_deserialize(&mut &data[..])?;
+        require_keys_eq!(reputation.wallet, *approver, ErrorCode::InvalidApproverReputation);
+        reputation.contracts_approved = reputation.contracts_approved.saturating_sub(1);
+        if *approver != contract.creator {
+            if let Some(role) = contract.role_of(approver) {
+                reputation.forget_deal_as(role);
+            }
+        }
+        reputation.try_serialize(&mut &mut data[..])?;
+    }
+    contract.approvers.clear();
+    contract.rejectors.clear();
+    contract.current_weight = 0;
+    if contract.status == ContractStatus::Completed {
+        contract.status = ContractStatus::Active;
+    }
+
+    amendment.status = AmendmentStatus::Applied;
+    amendment.applied_at = Some(Clock::get()?.unix_timestamp);
+
+    msg!("Contract {} is now at version {}", contract.contract_id, contract.version);
+    Ok(())
+}
+
+fn validate_participant_change(contract: &Contract, change: &ParticipantChange) -> Result<()> {
+    match change.action {
+        ParticipantAction::Add { role, weight } => {
+            Participant { wallet: change.participant, role, weight }.validate()?;
+            require!(
+                !contract.is_participant(&change.participant),
+                ErrorCode::AlreadyAParticipant
+            );
+            require!(
+                contract.participants.len() < Contract::MAX_PARTICIPANTS,
+                ErrorCode::TooManyParticipants
+            );
+            require!(
+                contract.arbiter != Some(change.participant),
+                ErrorCode::ArbiterIsParticipant
+            );
+        }
+        ParticipantAction::Remove => {
+            require!(
+                contract.is_participant(&change.participant),
+                ErrorCode::NotAParticipant
+            );
+            require!(
+                change.participant != contract.creator,
+                ErrorCode::CreatorMustBeParticipant
+            );
+            require!(
+                !contract.has_open_milestones(&change.participant),
+                ErrorCode::RecipientHasOpenMilestones
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Adds or removes the participant. A removed participant's contract approval is dropped,
+/// `required_weight` and `veto_threshold` are capped by those who remain, and the contract's
+/// approval outcome is re-evaluated against the new thresholds.
+fn apply_participant_change(contract: &mut Contract, change: &ParticipantChange) -> Result<()> {
+    validate_participant_change(contract, change)?;
+
+    match change.action {
+        ParticipantAction::Add { role, weight } => contract.participants.push(Participant {
+            wallet: change.participant,
+            role,
+            weight,
+        }),
+        ParticipantAction::Remove => {
+            contract.participants.retain(|participant| participant.wallet != change.participant);
+            contract.approvers.retain(|approver| *approver != change.participant);
+            contract.rejectors.retain(|rejector| *rejector != change.participant);
+            contract.current_weight = contract.approval_weight(&contract.approvers);
+            contract.required_weight = contract.required_weight.min(contract.total_weight()).max(1);
+            contract.veto_threshold = contract.veto_threshold.min(contract.approver_count() as u8).max(1);
+
+            if matches!(contract.status, ContractStatus::Active | ContractStatus::Completed) {
+                contract.status = if contract.rejectors.len() >= contract.veto_threshold as usize {
+                    ContractStatus::Rejected
+                } else if contract.meets_threshold(contract.current_weight) {
+                    ContractStatus::Completed
+                } else {
+                    ContractStatus::Active
+                };
+            }
+        }
+    }
+
+    msg!("Contract {} now has {} participants", contract.contract_id, contract.participants.len());
+    Ok(())
+}
+
+fn validate_escrow_terms(
+    contract: &Contract,
+    creator: &Pubkey,
+    description: &str,
+    amount: u64,
+    recipient: &Pubkey,
+) -> Result<()> {
+    require!(
+        description.len() <= 200,
+        ErrorCode::DescriptionTooLong
+    );
+    require!(
+        contract.status == ContractStatus::Active || contract.status == ContractStatus::Completed,
+        ErrorCode::ContractNotActive
+    );
+    require!(
+        contract.creator == *creator,
+        ErrorCode::OnlyCreatorCanInitializeEscrow
+    );
+    require!(
+        contract.is_vendor(recipient),
+        ErrorCode::RecipientNotVendor
+    );
+    require_keys_neq!(*recipient, *creator, ErrorCode::CreatorCannotBeRecipient);
+    require!(
+        amount > 0,
+        ErrorCode::InvalidAmount
+    );
+    Ok(())
+}
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Mint extensions an escrow vault can hold safely. Anything else (transfer hooks, permanent
+/// delegates, non-transferable or default-frozen accounts) could block or drain the vault.
+const SUPPORTED_MINT_EXTENSIONS: [ExtensionType; 9] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
+
+fn validate_escrow_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    for extension in mint_state.get_extension_types()? {
+        require!(
+            SUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+/// Amount that reaches the destination of a transfer once the Token-2022 transfer fee
+/// (if the mint has one) is withheld. Interest-bearing mints only change the UI amount,
+/// so raw amounts are recorded as-is.
+fn amount_after_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    let fee = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => fee_config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ErrorCode::InvalidAmount)?,
+        Err(_) => 0,
+    };
+    Ok(amount - fee)
+}
+
+/// Token-program accounts that accompany an SPL milestone; all `None` for native SOL.
+#[derive(Clone, Copy)]
+pub struct EscrowTokens<'a, 'info> {
+    pub vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    pub token_program: Option<&'a Interface<'info, TokenInterface>>,
+}
+
+impl<'a, 'info> EscrowTokens<'a, 'info> {
+    pub fn new(
+        vault: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+        mint: &'a Option<InterfaceAccount<'info, Mint>>,
+        token_program: &'a Option<Interface<'info, TokenInterface>>,
+    ) -> Self {
+        Self {
+            vault: vault.as_ref(),
+            mint: mint.as_ref(),
+            token_program: token_program.as_ref(),
+        }
+    }
+}
+
+fn validate_recipient_splits(
+    contract: &Contract,
+    creator: &Pubkey,
+    recipient: &Pubkey,
+    splits: &[RecipientShare],
+) -> Result<()> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+    require!(
+        splits.len() <= EscrowMilestone::MAX_SPLITS,
+        ErrorCode::TooManySplits
+    );
+    require!(
+        splits.iter().any(|share| share.wallet == *recipient),
+        ErrorCode::InvalidSplits
+    );
+    let mut total_bps = 0u64;
+    for (i, share) in splits.iter().enumerate() {
+        require!(
+            contract.is_vendor(&share.wallet),
+            ErrorCode::RecipientNotVendor
+        );
+        // The creator's reputation is already loaded as the client side of every payout
+        require_keys_neq!(share.wallet, *creator, ErrorCode::CreatorCannotBeRecipient);
+        require!(
+            share.share_bps > 0 && !splits[..i].iter().any(|other| other.wallet == share.wallet),
+            ErrorCode::InvalidSplits
+        );
+        total_bps += share.share_bps as u64;
+    }
+    require!(total_bps == BPS_DENOMINATOR, ErrorCode::InvalidSplits);
+    Ok(())
+}
+
+/// Pays `amount` to a milestone's recipients according to its splits and updates each
+/// recipient's reputation, returning the total received. The lead recipient uses the fixed
+/// `lead_destination`/`lead_reputation` accounts; co-recipients take a (destination,
+/// reputation) pair each from `co_recipient_accounts`, in split order. Rounding dust goes to
+/// the lead recipient. `completes_deal` marks the final payout of the milestone.
+fn pay_recipients<'info>(
+    escrow: &Account<'info, EscrowMilestone>,
+    tokens: EscrowTokens<'_, 'info>,
+    lead_destination: &AccountInfo<'info>,
+    lead_reputation: &mut UserReputation,
+    co_recipient_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    completes_deal: bool,
+) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    let mut co_recipient_accounts = co_recipient_accounts.iter();
+    let mut lead_amount = amount;
+    let mut total_delivered = 0;
+
+    for share in escrow.splits.iter().filter(|share| share.wallet != escrow.recipient) {
+        let share_amount = (amount as u128 * share.share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        lead_amount -= share_amount;
+
+        let (Some(destination), Some(reputation_info)) = (co_recipient_accounts.next(), co_recipient_accounts.next()) else {
+            return err!(ErrorCode::MissingRecipientAccounts);
+        };
+        let delivered = transfer_from_escrow(escrow, tokens, destination, &share.wallet, share_amount)?;
+        total_delivered += delivered;
+
+        require_keys_eq!(*reputation_info.owner, crate::ID, ErrorCode::InvalidRecipientReputation);
+        let mut data = reputation_info.try_borrow_mut_data()?;
+        let mut reputation = UserReputation::try_deserialize(&mut &data[..])?;
+        require_keys_eq!(reputation.wallet, share.wallet, ErrorCode::InvalidRecipientReputation);
+        if completes_deal {
+            reputation.deals_as_vendor += 1;
+            reputation.completed_as_vendor += 1;
+        }
+        reputation.record_value_transacted(escrow.mint, delivered);
+        reputation.last_activity = now;
+        reputation.try_serialize(&mut &mut data[..])?;
+    }
+
+    let delivered = transfer_from_escrow(escrow, tokens, lead_destination, &escrow.recipient, lead_amount)?;
+    total_delivered += delivered;
+    if completes_deal {
+        lead_reputation.completed_as_vendor += 1;
+    }
+    lead_reputation.record_value_transacted(escrow.mint, delivered);
+    lead_reputation.last_activity = now;
+
+    Ok(total_delivered)
+}
+
+/// Transfers `amount` from `from` into an escrow vault and returns what the vault received.
+fn deposit_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let cpi_context = CpiContext::new(
+        token_program.to_account_info(),
+        token_interface::TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: vault.to_account_info(),
+            authority: authority.to_account_info(),
+        },
+    );
+    token_interface::transfer_checked(cpi_context, amount, mint.decimals)?;
+    amount_after_transfer_fee(mint, amount)
+}
+
+/// Where refunds to a creator land: their token account for SPL milestones, else their wallet.
+fn refund_destination<'info>(
+    creator: &AccountInfo<'info>,
+    creator_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+) -> AccountInfo<'info> {
+    match creator_token_account {
+        Some(token_account) => token_account.to_account_info(),
+        None => creator.clone(),
+    }
+}
+
+/// Moves `amount` out of an escrow milestone to `wallet` and returns what `wallet` actually
+/// received. Native SOL milestones debit the escrow PDA's lamports directly; SPL milestones
+/// transfer from the vault, signed by the PDA, and `destination` must then be a token account
+/// of the escrow mint owned by `wallet`.
+fn transfer_from_escrow<'info, T: EscrowFunds + AccountSerialize + AccountDeserialize + Owner + Clone>(
+    escrow: &Account<'info, T>,
+    tokens: EscrowTokens<'_, 'info>,
+    destination: &AccountInfo<'info>,
+    wallet: &Pubkey,
+    amount: u64,
+) -> Result<u64> {
+    let Some(escrow_mint) = escrow.funds_mint() else {
+        require_keys_eq!(destination.key(), *wallet, ErrorCode::InvalidDestination);
+        **escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **destination.try_borrow_mut_lamports()? += amount;
+        return Ok(amount);
+    };
+
+    let (Some(vault), Some(mint), Some(token_program)) = (tokens.vault, tokens.mint, tokens.token_program) else {
+        return err!(ErrorCode::MissingTokenAccounts);
+    };
+    require_keys_eq!(mint.key(), escrow_mint, ErrorCode::InvalidMint);
+    require_keys_eq!(*destination.owner, token_program.key(), ErrorCode::InvalidDestination);
+    let destination_account = TokenAccount::try_deserialize(&mut &destination.try_borrow_data()?[..])?;
+    require!(
+        destination_account.mint == escrow_mint && destination_account.owner == *wallet,
+        ErrorCode::InvalidDestination
+    );
+
+    escrow.with_signer_seeds(|signer_seeds| {
+        let cpi_context = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to: destination.clone(),
+                authority: escrow.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, amount, mint.decimals)
+    })?;
+
+    amount_after_transfer_fee(mint, amount)
+}
+
+/// Closes an SPL milestone's drained vault, sending its rent to `destination`. A Token-2022
+/// vault still holding withheld transfer fees cannot be closed and is left as is.
+fn close_escrow_vault<'info, T: EscrowFunds + AccountSerialize + AccountDeserialize + Owner + Clone>(
+    escrow: &Account<'info, T>,
+    tokens: EscrowTokens<'_, 'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let (Some(vault), Some(token_program)) = (tokens.vault, tokens.token_program) else {
+        return Ok(());
+    };
+    {
+        let vault_info = vault.to_account_info();
+        let vault_data = vault_info.try_borrow_data()?;
+        let vault_state = StateWithExtensions::<TokenAccountState>::unpack(&vault_data)?;
+        let withheld = vault_state
+            .get_extension::<TransferFeeAmount>()
+            .map_or(0, |fees| u64::from(fees.withheld_amount));
+        if vault_state.base.amount > 0 || withheld > 0 {
+            return Ok(());
+        }
+    }
+
+    escrow.with_signer_seeds(|signer_seeds| {
+        let cpi_context = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account: vault.to_account_info(),
+                destination: destination.clone(),
+                authority: escrow.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::close_account(cpi_context)
+    })
+}
+
+#[derive(Accounts)]
+pub struct InitializeReputation<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = UserReputation::LEN,
+        seeds = [b"reputation", user.key().as_ref()],
+        bump
+    )]
+    pub reputation: Account<'info, UserReputation>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(contract_id: u64)]
+pub struct InitializeContract<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Contract::LEN,
+        seeds = [b"contract", contract_id.to_le_bytes().as_ref(), creator.key().as_ref()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+    
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
+    /// CHECK: First amendment slot for this contract address. Amendment records outlive a
+    /// closed contract, so a non-empty slot means the id was used before
+    #[account(seeds = [b"amendment", contract.key().as_ref(), 2u32.to_le_bytes().as_ref()], bump)]
+    pub first_amendment: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+    
+    #[account(
+        mut,
+        seeds = [b"reputation", approver.key().as_ref()],
+        bump = approver_reputation.bump,
+    )]
+    pub approver_reputation: Account<'info, UserReputation>,
+    
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeApproval<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", approver.key().as_ref()],
+        bump = approver_reputation.bump,
+    )]
+    pub approver_reputation: Account<'info, UserReputation>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RejectContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub rejector: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MarkContractComplete<'info> {
+    #[account(
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+    
+    #[account(
+        mut,
+        seeds = [b"reputation", participant.key().as_ref()],
+        bump = participant_reputation.bump,
+    )]
+    pub participant_reputation: Account<'info, UserReputation>,
+    
+    /// CHECK: We verify their role in contract.participants
+    pub participant: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+    
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+    
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", contract.creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+}
+
+#[derive(Accounts)]
+pub struct CloseContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump,
+        has_one = creator @ ErrorCode::OnlyCreatorCanClose,
+        close = creator
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetContractDocument<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+    
+    pub updater: Signer<'info>,
+}
+
+// ========== CONTRACT AMENDMENT ACCOUNT STRUCTURES ==========
+
+#[derive(Accounts)]
+pub struct ProposeContractAmendment<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = ContractAmendment::LEN,
+        seeds = [b"amendment", contract.key().as_ref(), (contract.version + 1).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub amendment: Account<'info, ContractAmendment>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveContractAmendment<'info> {
+    #[account(
+        mut,
+        seeds = [b"amendment", contract.key().as_ref(), amendment.version.to_le_bytes().as_ref()],
+        bump = amendment.bump
+    )]
+    pub amendment: Account<'info, ContractAmendment>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawContractAmendment<'info> {
+    #[account(
+        mut,
+        seeds = [b"amendment", contract.key().as_ref(), amendment.version.to_le_bytes().as_ref()],
+        bump = amendment.bump,
+        has_one = proposer @ ErrorCode::OnlyProposerCanWithdraw,
+        close = proposer
+    )]
+    pub amendment: Account<'info, ContractAmendment>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+// ========== PARTICIPANT CHANGE ACCOUNT STRUCTURES ==========
+
+#[derive(Accounts)]
+#[instruction(participant: Pubkey)]
+pub struct ProposeParticipantChange<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = ParticipantChange::LEN,
+        seeds = [b"participant_change", contract.key().as_ref(), participant.as_ref()],
+        bump
+    )]
+    pub participant_change: Account<'info, ParticipantChange>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveParticipantChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"participant_change", contract.key().as_ref(), participant_change.participant.as_ref()],
+        bump = participant_change.bump
+    )]
+    pub participant_change: Account<'info, ParticipantChange>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    /// CHECK: Receives the proposal's rent once applied, must be the proposer
+    #[account(mut, address = participant_change.proposer)]
+    pub proposer: AccountInfo<'info>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawParticipantChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"participant_change", contract.key().as_ref(), participant_change.participant.as_ref()],
+        bump = participant_change.bump,
+        has_one = proposer @ ErrorCode::OnlyProposerCanWithdraw,
+        close = proposer
+    )]
+    pub participant_change: Account<'info, ParticipantChange>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+// ========== ESCROW MILESTONE ACCOUNT STRUCTURES ==========
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u64, contract_id: u64)]
+pub struct InitializeEscrowMilestone<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = EscrowMilestone::LEN,
+        seeds = [b"escrow", contract.key().as_ref(), milestone_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump,
+        constraint = contract.contract_id == contract_id @ ErrorCode::ContractMismatch
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u64, contract_id: u64)]
+pub struct InitializeTokenEscrowMilestone<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = EscrowMilestone::LEN,
+        seeds = [b"escrow", contract.key().as_ref(), milestone_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = escrow_milestone,
+        token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = creator,
+        token::token_program = token_program,
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump,
+        constraint = contract.contract_id == contract_id @ ErrorCode::ContractMismatch
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MarkMilestoneComplete<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    pub marker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMilestoneRelease<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", approver.key().as_ref()],
+        bump = approver_reputation.bump,
+    )]
+    pub approver_reputation: Account<'info, UserReputation>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseEscrowFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    /// CHECK: Recipient wallet, or the recipient's token account for SPL milestones.
+    /// Verified against escrow_milestone.recipient in transfer_from_escrow
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.creator.key().as_ref()],
+        bump = client_reputation.bump,
+    )]
+    pub client_reputation: Account<'info, UserReputation>,
+
+    /// CHECK: Receives late-delivery penalties on SOL milestones, must be the escrow creator
+    #[account(mut, address = escrow_milestone.creator)]
+    pub creator: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ReleasePartialEscrowFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    /// CHECK: Recipient wallet, or the recipient's token account for SPL milestones.
+    /// Verified against escrow_milestone.recipient in transfer_from_escrow
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.creator.key().as_ref()],
+        bump = client_reputation.bump,
+    )]
+    pub client_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEscrowMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    /// CHECK: Refund destination for SOL milestones, must be the escrow creator
+    #[account(mut, address = escrow_milestone.creator)]
+    pub creator: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEscrowMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = creator @ ErrorCode::OnlyCreatorCanClose,
+        close = creator
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseRetention<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    /// CHECK: Recipient wallet, or the recipient's token account for SPL milestones.
+    /// Verified against escrow_milestone.recipient in transfer_from_escrow
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+// ========== MILESTONE AMENDMENT ACCOUNT STRUCTURES ==========
+
+#[derive(Accounts)]
+pub struct ProposeMilestoneAmendment<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = MilestoneAmendment::LEN,
+        seeds = [b"milestone_amendment", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub amendment: Account<'info, MilestoneAmendment>,
+
+    #[account(
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMilestoneAmendment<'info> {
+    #[account(
+        mut,
+        seeds = [b"milestone_amendment", escrow_milestone.key().as_ref()],
+        bump = amendment.bump
+    )]
+    pub amendment: Account<'info, MilestoneAmendment>,
+
+    #[account(
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyMilestoneAmendment<'info> {
+    #[account(
+        mut,
+        seeds = [b"milestone_amendment", escrow_milestone.key().as_ref()],
+        bump = amendment.bump,
+        close = proposer
+    )]
+    pub amendment: Account<'info, MilestoneAmendment>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    /// CHECK: Receives the amendment account's rent, must be the proposer
+    #[account(mut, address = amendment.proposer)]
+    pub proposer: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawMilestoneAmendment<'info> {
+    #[account(
+        mut,
+        seeds = [b"milestone_amendment", escrow_milestone.key().as_ref()],
+        bump = amendment.bump,
+        has_one = proposer @ ErrorCode::OnlyProposerCanWithdraw,
+        close = proposer
+    )]
+    pub amendment: Account<'info, MilestoneAmendment>,
+
+    /// CHECK: Only used to derive the amendment PDA; may already be closed
+    pub escrow_milestone: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+// ========== VESTING MILESTONE ACCOUNT STRUCTURES ==========
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u64, contract_id: u64)]
+pub struct InitializeVestingMilestone<'info> {
+    #[account(
         init,
-        payer = user,
-        space = UserReputation::LEN,
-        seeds = [b"reputation", user.key().as_ref()],
+        payer = creator,
+        space = VestingMilestone::LEN,
+        seeds = [b"vesting", contract.key().as_ref(), milestone_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub reputation: Account<'info, UserReputation>,
-    
+    pub vesting_milestone: Account<'info, VestingMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump,
+        constraint = contract.contract_id == contract_id @ ErrorCode::ContractMismatch
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
     #[account(mut)]
-    pub user: Signer<'info>,
-    
+    pub creator: Signer<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(contract_id: u64)]
-pub struct InitializeContract<'info> {
+#[instruction(milestone_id: u64, contract_id: u64)]
+pub struct InitializeTokenVestingMilestone<'info> {
     #[account(
         init,
         payer = creator,
-        space = Contract::LEN,
-        seeds = [b"contract", contract_id.to_le_bytes().as_ref(), creator.key().as_ref()],
+        space = VestingMilestone::LEN,
+        seeds = [b"vesting", contract.key().as_ref(), milestone_id.to_le_bytes().as_ref()],
         bump
     )]
+    pub vesting_milestone: Account<'info, VestingMilestone>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"vault", vesting_milestone.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vesting_milestone,
+        token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = creator,
+        token::token_program = token_program,
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
+        bump = contract.bump,
+        constraint = contract.contract_id == contract_id @ ErrorCode::ContractMismatch
+    )]
     pub contract: Account<'info, Contract>,
-    
+
     #[account(
         mut,
         seeds = [b"reputation", creator.key().as_ref()],
         bump = creator_reputation.bump,
     )]
     pub creator_reputation: Account<'info, UserReputation>,
-    
+
     #[account(mut)]
     pub creator: Signer<'info>,
-    
+
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ApproveContract<'info> {
+pub struct ClaimVestedFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"vesting", vesting_milestone.contract.as_ref(), vesting_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = vesting_milestone.bump
+    )]
+    pub vesting_milestone: Account<'info, VestingMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelVestingMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"vesting", vesting_milestone.contract.as_ref(), vesting_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = vesting_milestone.bump
+    )]
+    pub vesting_milestone: Account<'info, VestingMilestone>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", creator.key().as_ref()],
+        bump = creator_reputation.bump,
+    )]
+    pub creator_reputation: Account<'info, UserReputation>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_milestone.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct RateVendor<'info> {
+    #[account(
+        mut,
+        seeds = [b"reputation", vendor_reputation.wallet.key().as_ref()],
+        bump = vendor_reputation.bump,
+    )]
+    pub vendor_reputation: Account<'info, UserReputation>,
+
+    #[account(
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    /// One rating per rater, ratee and milestone
+    #[account(
+        init,
+        payer = rater,
+        space = RatingReceipt::LEN,
+        seeds = [b"rating", escrow_milestone.key().as_ref(), rater.key().as_ref(), vendor_reputation.wallet.as_ref()],
+        bump
+    )]
+    pub rating_receipt: Account<'info, RatingReceipt>,
+
+    #[account(mut)]
+    pub rater: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RateClient<'info> {
+    #[account(
+        mut,
+        seeds = [b"reputation", client_reputation.wallet.key().as_ref()],
+        bump = client_reputation.bump,
+    )]
+    pub client_reputation: Account<'info, UserReputation>,
+
+    #[account(
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    /// One rating per rater, ratee and milestone
+    #[account(
+        init,
+        payer = rater,
+        space = RatingReceipt::LEN,
+        seeds = [b"rating", escrow_milestone.key().as_ref(), rater.key().as_ref(), client_reputation.wallet.as_ref()],
+        bump
+    )]
+    pub rating_receipt: Account<'info, RatingReceipt>,
+
+    #[account(mut)]
+    pub rater: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// ========== DISPUTE ACCOUNT STRUCTURES ==========
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(
+        init,
+        payer = claimant,
+        space = Dispute::LEN,
+        seeds = [b"dispute", escrow_milestone.key().as_ref(), escrow_milestone.dispute_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(
+        init,
+        payer = claimant,
+        space = EvidenceLog::LEN,
+        seeds = [b"evidence", dispute.key().as_ref()],
+        bump
+    )]
+    pub evidence_log: Account<'info, EvidenceLog>,
+
     #[account(
         mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
         seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
         bump = contract.bump
     )]
     pub contract: Account<'info, Contract>,
-    
+
+    #[account(
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ArbiterRegistry>,
+
+    #[account(
+        seeds = [b"arbiter", contract_arbiter.wallet.as_ref()],
+        bump = contract_arbiter.bump
+    )]
+    pub contract_arbiter: Option<Account<'info, Arbiter>>,
+
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitEvidence<'info> {
+    #[account(
+        mut,
+        seeds = [b"evidence", dispute.key().as_ref()],
+        bump = evidence_log.bump,
+        has_one = dispute
+    )]
+    pub evidence_log: Account<'info, EvidenceLog>,
+
+    pub dispute: Account<'info, Dispute>,
+
+    pub submitter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AssignDisputePanel<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(address = dispute.escrow_milestone)]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump,
+        has_one = authority @ ErrorCode::OnlyRegistryAuthority
+    )]
+    pub registry: Account<'info, ArbiterRegistry>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoteOnDispute<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(
+        mut,
+        seeds = [b"arbiter", arbiter.key().as_ref()],
+        bump = arbiter_account.bump
+    )]
+    pub arbiter_account: Account<'info, Arbiter>,
+
+    #[account(
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ArbiterRegistry>,
+
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FundDisputeFees<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppealRuling<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut, address = dispute.escrow_milestone)]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    #[account(
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ArbiterRegistry>,
+
+    #[account(mut)]
+    pub appellant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRuling<'info> {
+    #[account(
+        mut,
+        seeds = [b"dispute", escrow_milestone.key().as_ref(), dispute.index.to_le_bytes().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
     #[account(
         mut,
-        seeds = [b"reputation", approver.key().as_ref()],
-        bump = approver_reputation.bump,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump,
+        has_one = contract @ ErrorCode::ContractMismatch
     )]
-    pub approver_reputation: Account<'info, UserReputation>,
-    
-    pub approver: Signer<'info>,
-}
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
 
-#[derive(Accounts)]
-pub struct MarkContractComplete<'info> {
     #[account(
+        mut,
         seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
         bump = contract.bump
     )]
     pub contract: Account<'info, Contract>,
-    
+
+    /// CHECK: Recipient wallet, or the recipient's token account for SPL milestones.
+    /// Verified against escrow_milestone.recipient in transfer_from_escrow
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: Refund destination for SOL milestones, must be the escrow creator
+    #[account(mut, address = escrow_milestone.creator)]
+    pub creator: AccountInfo<'info>,
+
     #[account(
         mut,
-        seeds = [b"reputation", participant.key().as_ref()],
-        bump = participant_reputation.bump,
+        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
+        bump = vendor_reputation.bump,
     )]
-    pub participant_reputation: Account<'info, UserReputation>,
-    
-    /// CHECK: We verify they're in contract.participants
-    pub participant: AccountInfo<'info>,
-}
+    pub vendor_reputation: Account<'info, UserReputation>,
 
-#[derive(Accounts)]
-pub struct CancelContract<'info> {
     #[account(
         mut,
-        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
-        bump = contract.bump
+        seeds = [b"reputation", escrow_milestone.creator.key().as_ref()],
+        bump = client_reputation.bump,
     )]
-    pub contract: Account<'info, Contract>,
-    
+    pub client_reputation: Account<'info, UserReputation>,
+
+    /// CHECK: Receives what is left of the fee pool, must be the claimant
+    #[account(mut, address = dispute.claimant)]
+    pub claimant: AccountInfo<'info>,
+
+    /// CHECK: Receives a posted appeal fee; checked against the dispute's outcome in the handler
+    #[account(mut)]
+    pub appeal_fee_recipient: Option<AccountInfo<'info>>,
+
     #[account(
         mut,
-        seeds = [b"reputation", creator.key().as_ref()],
-        bump = creator_reputation.bump,
+        seeds = [b"vault", escrow_milestone.key().as_ref()],
+        bump
     )]
-    pub creator_reputation: Account<'info, UserReputation>,
-    
-    pub creator: Signer<'info>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub cranker: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateContractIpfs<'info> {
+pub struct ResolveStalledDispute<'info> {
     #[account(
         mut,
-        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
-        bump = contract.bump
+        seeds = [b"dispute", escrow_milestone.key().as_ref(), dispute.index.to_le_bytes().as_ref()],
+        bump = dispute.bump
     )]
-    pub contract: Account<'info, Contract>,
-    
-    pub updater: Signer<'info>,
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow_milestone.contract.as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
+        bump = escrow_milestone.bump
+    )]
+    pub escrow_milestone: Account<'info, EscrowMilestone>,
+
+    /// CHECK: Receives what is left of the fee pool, must be the claimant
+    #[account(mut, address = dispute.claimant)]
+    pub claimant: AccountInfo<'info>,
+
+    /// CHECK: Receives a posted appeal fee; checked against dispute.appellant in the handler
+    #[account(mut)]
+    pub appellant: Option<AccountInfo<'info>>,
+
+    pub caller: Signer<'info>,
 }
 
-// ========== ESCROW MILESTONE ACCOUNT STRUCTURES ==========
+// ========== ARBITER ACCOUNT STRUCTURES ==========
 
 #[derive(Accounts)]
-#[instruction(milestone_id: u64, contract_id: u64)]
-pub struct InitializeEscrowMilestone<'info> {
+pub struct RegisterArbiter<'info> {
     #[account(
         init,
-        payer = creator,
-        space = EscrowMilestone::LEN,
-        seeds = [b"escrow", contract_id.to_le_bytes().as_ref(), milestone_id.to_le_bytes().as_ref()],
+        payer = wallet,
+        space = Arbiter::LEN,
+        seeds = [b"arbiter", wallet.key().as_ref()],
         bump
     )]
-    pub escrow_milestone: Account<'info, EscrowMilestone>,
-
-    #[account(
-        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
-        bump = contract.bump
-    )]
-    pub contract: Account<'info, Contract>,
+    pub arbiter: Account<'info, Arbiter>,
 
     #[account(
-        mut,
-        seeds = [b"reputation", creator.key().as_ref()],
-        bump = creator_reputation.bump,
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump
     )]
-    pub creator_reputation: Account<'info, UserReputation>,
+    pub registry: Account<'info, ArbiterRegistry>,
 
     #[account(mut)]
-    pub creator: Signer<'info>,
+    pub wallet: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct MarkMilestoneComplete<'info> {
+pub struct RegisterTokenArbiter<'info> {
     #[account(
-        mut,
-        seeds = [b"escrow", escrow_milestone.contract_id.to_le_bytes().as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
-        bump = escrow_milestone.bump
+        init,
+        payer = wallet,
+        space = Arbiter::LEN,
+        seeds = [b"arbiter", wallet.key().as_ref()],
+        bump
     )]
-    pub escrow_milestone: Account<'info, EscrowMilestone>,
+    pub arbiter: Account<'info, Arbiter>,
 
     #[account(
-        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
-        bump = contract.bump
+        init,
+        payer = wallet,
+        seeds = [b"arbiter_vault", arbiter.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = arbiter,
+        token::token_program = token_program,
     )]
-    pub contract: Account<'info, Contract>,
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
-        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
-        bump = vendor_reputation.bump,
+        token::mint = mint,
+        token::authority = wallet,
+        token::token_program = token_program,
     )]
-    pub vendor_reputation: Account<'info, UserReputation>,
+    pub wallet_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub marker: Signer<'info>,
+    #[account(
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ArbiterRegistry>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ApproveMilestoneRelease<'info> {
+pub struct AddArbiterStake<'info> {
     #[account(
         mut,
-        seeds = [b"escrow", escrow_milestone.contract_id.to_le_bytes().as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
-        bump = escrow_milestone.bump
+        seeds = [b"arbiter", wallet.key().as_ref()],
+        bump = arbiter.bump
     )]
-    pub escrow_milestone: Account<'info, EscrowMilestone>,
+    pub arbiter: Account<'info, Arbiter>,
 
-    #[account(
-        seeds = [b"contract", contract.contract_id.to_le_bytes().as_ref(), contract.creator.key().as_ref()],
-        bump = contract.bump
-    )]
-    pub contract: Account<'info, Contract>,
+    #[account(mut)]
+    pub wallet: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"reputation", approver.key().as_ref()],
-        bump = approver_reputation.bump,
+        seeds = [b"arbiter_vault", arbiter.key().as_ref()],
+        bump
     )]
-    pub approver_reputation: Account<'info, UserReputation>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    pub approver: Signer<'info>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub wallet_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ReleaseEscrowFunds<'info> {
+pub struct UpdateArbiter<'info> {
     #[account(
         mut,
-        seeds = [b"escrow", escrow_milestone.contract_id.to_le_bytes().as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
-        bump = escrow_milestone.bump
+        seeds = [b"arbiter", wallet.key().as_ref()],
+        bump = arbiter.bump
     )]
-    pub escrow_milestone: Account<'info, EscrowMilestone>,
+    pub arbiter: Account<'info, Arbiter>,
 
-    /// CHECK: Verified via escrow_milestone.recipient
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
+    pub wallet: Signer<'info>,
+}
 
+#[derive(Accounts)]
+pub struct WithdrawArbiterStake<'info> {
     #[account(
         mut,
-        seeds = [b"reputation", escrow_milestone.recipient.key().as_ref()],
-        bump = vendor_reputation.bump,
+        seeds = [b"arbiter", wallet.key().as_ref()],
+        bump = arbiter.bump
     )]
-    pub vendor_reputation: Account<'info, UserReputation>,
+    pub arbiter: Account<'info, Arbiter>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"reputation", escrow_milestone.creator.key().as_ref()],
-        bump = client_reputation.bump,
+        seeds = [b"arbiter_vault", arbiter.key().as_ref()],
+        bump
     )]
-    pub client_reputation: Account<'info, UserReputation>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub wallet_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[derive(Accounts)]
-pub struct CancelEscrowMilestone<'info> {
+pub struct SlashArbiter<'info> {
     #[account(
         mut,
-        seeds = [b"escrow", escrow_milestone.contract_id.to_le_bytes().as_ref(), escrow_milestone.milestone_id.to_le_bytes().as_ref()],
-        bump = escrow_milestone.bump
+        seeds = [b"arbiter", arbiter.wallet.as_ref()],
+        bump = arbiter.bump
     )]
-    pub escrow_milestone: Account<'info, EscrowMilestone>,
+    pub arbiter: Account<'info, Arbiter>,
+
+    #[account(
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump,
+        has_one = authority @ ErrorCode::OnlyRegistryAuthority
+    )]
+    pub registry: Account<'info, ArbiterRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"reputation", creator.key().as_ref()],
-        bump = creator_reputation.bump,
+        seeds = [b"arbiter_vault", arbiter.key().as_ref()],
+        bump
     )]
-    pub creator_reputation: Account<'info, UserReputation>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
 
     #[account(mut)]
-    pub creator: Signer<'info>,
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[derive(Accounts)]
-pub struct RateVendor<'info> {
+pub struct RecordOverturnedRuling<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+
     #[account(
         mut,
-        seeds = [b"reputation", vendor_reputation.wallet.key().as_ref()],
-        bump = vendor_reputation.bump,
+        seeds = [b"arbiter", arbiter.wallet.as_ref()],
+        bump = arbiter.bump
     )]
-    pub vendor_reputation: Account<'info, UserReputation>,
-
-    pub rater: Signer<'info>,
+    pub arbiter: Account<'info, Arbiter>,
 }
 
+// ========== ARBITER REGISTRY ACCOUNT STRUCTURES ==========
+
 #[derive(Accounts)]
-pub struct RateClient<'info> {
+pub struct InitializeArbiterRegistry<'info> {
     #[account(
-        mut,
-        seeds = [b"reputation", client_reputation.wallet.key().as_ref()],
-        bump = client_reputation.bump,
+        init,
+        payer = authority,
+        space = ArbiterRegistry::LEN,
+        seeds = [b"arbiter_registry"],
+        bump
     )]
-    pub client_reputation: Account<'info, UserReputation>,
+    pub registry: Account<'info, ArbiterRegistry>,
 
-    pub rater: Signer<'info>,
+    /// Only the program's upgrade authority can claim the singleton registry
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::AgreedContracts>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ ErrorCode::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ReportDispute<'info> {
+pub struct UpdateArbiterRegistry<'info> {
     #[account(
         mut,
-        seeds = [b"reputation", reputation.wallet.key().as_ref()],
-        bump = reputation.bump,
+        seeds = [b"arbiter_registry"],
+        bump = registry.bump,
+        has_one = authority @ ErrorCode::OnlyRegistryAuthority
     )]
-    pub reputation: Account<'info, UserReputation>,
+    pub registry: Account<'info, ArbiterRegistry>,
 
-    pub reporter: Signer<'info>,
+    pub authority: Signer<'info>,
 }
 
 #[account]
 pub struct Contract {
     pub contract_id: u64,
     pub creator: Pubkey,
-    pub participants: Vec<Pubkey>,
+    pub participants: Vec<Participant>,
     pub status: ContractStatus,
-    pub required_approvals: u8,
-    pub current_approvals: u8,
+    pub required_weight: u32, // Combined approver weight needed to complete the contract
+    pub current_weight: u32,
     pub approvers: Vec<Pubkey>,
-    pub ipfs_hash: String,
+    pub document: Option<DocumentRef>, // None until the first document is set
+    pub created_at: i64,
+    pub bump: u8,
+    pub arbiter: Option<Pubkey>, // Rules on disputes over this contract's milestones
+    pub version: u32,            // Document version; 0 until the first document is set
+    pub previous_version_hash: [u8; 32], // DocumentRef::fingerprint of the previous version
+    pub veto_threshold: u8,   // Rejections that move the contract to Rejected
+    pub rejectors: Vec<Pubkey>,
+    pub approval_deadline: Option<i64>, // After this, approvals stop and the contract can expire
+    pub milestone_count: u32,           // Escrow and vesting milestones funded under this contract
+    pub ordered_signing: bool,          // Approvals must follow the order of `participants`
+    pub open_milestones: Vec<OpenMilestones>, // Unsettled escrow milestones per recipient
+    pub pending_proposals: u32,         // Amendments and participant changes awaiting approval
+}
+
+impl Contract {
+    pub const MAX_PARTICIPANTS: usize = 10;
+    pub const LEN: usize = 8 + // discriminator
+        8 + // contract_id
+        32 + // creator
+        (4 + Participant::LEN * Self::MAX_PARTICIPANTS) + // participants vec
+        1 + // status
+        4 + // required_weight
+        4 + // current_weight
+        (4 + 32 * Self::MAX_PARTICIPANTS) + // approvers vec
+        (1 + DocumentRef::LEN) + // document option
+        8 + // created_at
+        1 + // bump
+        (1 + 32) + // arbiter option
+        4 + // version
+        32 + // previous_version_hash
+        1 + // veto_threshold
+        (4 + 32 * Self::MAX_PARTICIPANTS) + // rejectors vec
+        (1 + 8) + // approval_deadline option
+        4 + // milestone_count
+        1 + // ordered_signing
+        (4 + OpenMilestones::LEN * Self::MAX_PARTICIPANTS) + // open_milestones vec
+        4; // pending_proposals
+
+    /// With ordered signing, the first participant in `participants` order who can approve
+    /// and hasn't yet. Rejectors have cast their vote and no longer hold up the queue.
+    pub fn next_signer(&self) -> Option<Pubkey> {
+        self.participants
+            .iter()
+            .filter(|participant| participant.role != ParticipantRole::Observer)
+            .map(|participant| participant.wallet)
+            .find(|wallet| !self.approvers.contains(wallet) && !self.rejectors.contains(wallet))
+    }
+
+    /// Whether approvals and rejections are still accepted at `now`.
+    pub fn approval_window_open(&self, now: i64) -> bool {
+        self.approval_deadline.is_none_or(|deadline| now <= deadline)
+    }
+
+    /// Whether approvals carrying `weight` are enough to act on the contract's behalf.
+    pub fn meets_threshold(&self, weight: u32) -> bool {
+        weight >= self.required_weight
+    }
+
+    /// Combined weight of those `wallets` that are still participants whose approval counts.
+    pub fn approval_weight(&self, wallets: &[Pubkey]) -> u32 {
+        wallets.iter().map(|wallet| self.weight_of(wallet)).sum()
+    }
+
+    /// The approval weight `wallet` carries; zero for observers and non-participants.
+    pub fn weight_of(&self, wallet: &Pubkey) -> u32 {
+        self.participants
+            .iter()
+            .find(|participant| participant.wallet == *wallet && participant.role != ParticipantRole::Observer)
+            .map_or(0, |participant| participant.weight as u32)
+    }
+
+    /// Combined weight of every participant whose approval counts.
+    pub fn total_weight(&self) -> u32 {
+        self.participants.iter().map(|participant| self.weight_of(&participant.wallet)).sum()
+    }
+
+    pub fn role_of(&self, wallet: &Pubkey) -> Option<ParticipantRole> {
+        self.participants
+            .iter()
+            .find(|participant| participant.wallet == *wallet)
+            .map(|participant| participant.role)
+    }
+
+    pub fn is_participant(&self, wallet: &Pubkey) -> bool {
+        self.role_of(wallet).is_some()
+    }
+
+    pub fn is_vendor(&self, wallet: &Pubkey) -> bool {
+        self.role_of(wallet) == Some(ParticipantRole::Vendor)
+    }
+
+    /// Number of participants who can approve; observers only follow the contract.
+    pub fn approver_count(&self) -> usize {
+        self.participants
+            .iter()
+            .filter(|participant| participant.role != ParticipantRole::Observer)
+            .count()
+    }
+
+    /// Whether every current approver is among `approvals`. Approvals from wallets that have
+    /// since left the contract don't count.
+    pub fn all_approved(&self, approvals: &[Pubkey]) -> bool {
+        self.participants
+            .iter()
+            .filter(|participant| participant.role != ParticipantRole::Observer)
+            .all(|participant| approvals.contains(&participant.wallet))
+    }
+
+    /// Whether `wallet` is still owed an escrow milestone that hasn't settled.
+    pub fn has_open_milestones(&self, wallet: &Pubkey) -> bool {
+        self.open_milestones.iter().any(|open| open.wallet == *wallet)
+    }
+
+    /// Counts a newly funded escrow milestone against each of its recipients.
+    pub fn open_milestone(&mut self, escrow: &EscrowMilestone) {
+        for wallet in escrow.recipients() {
+            match self.open_milestones.iter_mut().find(|open| open.wallet == wallet) {
+                Some(open) => open.count += 1,
+                None => self.open_milestones.push(OpenMilestones { wallet, count: 1 }),
+            }
+        }
+    }
+
+    /// Drops a settled (released, cancelled, expired or resolved) escrow milestone from its
+    /// recipients' counts.
+    pub fn settle_milestone(&mut self, escrow: &EscrowMilestone) {
+        for wallet in escrow.recipients() {
+            if let Some(open) = self.open_milestones.iter_mut().find(|open| open.wallet == wallet) {
+                open.count -= 1;
+            }
+        }
+        self.open_milestones.retain(|open| open.count > 0);
+    }
+
+    /// Returns `wallet`'s role if it may approve on this contract.
+    pub fn require_approver(&self, wallet: &Pubkey) -> Result<ParticipantRole> {
+        let role = self.role_of(wallet).ok_or(ErrorCode::NotAParticipant)?;
+        require!(role != ParticipantRole::Observer, ErrorCode::ObserverCannotApprove);
+        Ok(role)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct Participant {
+    pub wallet: Pubkey,
+    pub role: ParticipantRole,
+    pub weight: u16, // Approval weight; ignored for observers
+}
+
+impl Participant {
+    pub const LEN: usize = 32 + 1 + 2;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.role == ParticipantRole::Observer || self.weight > 0,
+            ErrorCode::InvalidParticipantWeight
+        );
+        Ok(())
+    }
+}
+
+/// How many unsettled escrow milestones pay `wallet`; such a vendor can't be removed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OpenMilestones {
+    pub wallet: Pubkey,
+    pub count: u32,
+}
+
+impl OpenMilestones {
+    pub const LEN: usize = 32 + 4;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ParticipantRole {
+    Client,
+    Vendor,
+    Reviewer,
+    Observer,
+}
+
+/// A pending proposal to add or remove a contract participant, closed once applied.
+#[account]
+pub struct ParticipantChange {
+    pub contract: Pubkey,
+    pub proposer: Pubkey,
+    pub participant: Pubkey,
+    pub action: ParticipantAction,
+    pub approvals: Vec<Pubkey>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl ParticipantChange {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // contract
+        32 + // proposer
+        32 + // participant
+        (1 + 1 + 2) + // action enum with role and weight
+        (4 + 32 * Contract::MAX_PARTICIPANTS) + // approvals vec
+        8 + // created_at
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum ParticipantAction {
+    Add { role: ParticipantRole, weight: u16 },
+    Remove,
+}
+
+/// A proposed next version of a contract's document, kept as history once applied.
+#[account]
+pub struct ContractAmendment {
+    pub contract: Pubkey,
+    pub proposer: Pubkey,
+    pub version: u32,               // Version the contract moves to when applied
+    pub previous_document: DocumentRef, // Document being replaced
+    pub document: DocumentRef,
+    pub approvals: Vec<Pubkey>,
+    pub status: AmendmentStatus,
     pub created_at: i64,
+    pub applied_at: Option<i64>,
     pub bump: u8,
 }
 
-impl Contract {
-    pub const MAX_PARTICIPANTS: usize = 10;
+impl ContractAmendment {
     pub const LEN: usize = 8 + // discriminator
-        8 + // contract_id
-        32 + // creator
-        (4 + 32 * Self::MAX_PARTICIPANTS) + // participants vec
-        1 + // status
-        1 + // required_approvals
-        1 + // current_approvals
-        (4 + 32 * Self::MAX_PARTICIPANTS) + // approvers vec
-        (4 + 46) + // ipfs_hash
+        32 + // contract
+        32 + // proposer
+        4 + // version
+        DocumentRef::LEN + // previous_document
+        DocumentRef::LEN + // document
+        (4 + 32 * Contract::MAX_PARTICIPANTS) + // approvals vec
+        1 + // status enum
         8 + // created_at
+        (1 + 8) + // applied_at option
         1; // bump
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum AmendmentStatus {
+    Pending,
+    Applied,
+}
+
+/// Content address of an off-chain document, stored as raw bytes rather than its
+/// base-encoded text form. `Sha256` lets a client check a local file directly against
+/// the chain without reproducing IPFS chunking.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum DocumentRef {
+    CidV0([u8; 34]), // sha2-256 multihash: 0x12 0x20 followed by the digest
+    CidV1(Vec<u8>),  // Binary CID: version, codec and multihash varints
+    Sha256([u8; 32]),
+}
+
+impl DocumentRef {
+    pub const MAX_CID_V1_LEN: usize = 64;
+    pub const LEN: usize = 1 + (4 + Self::MAX_CID_V1_LEN); // variant tag + largest variant
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            DocumentRef::CidV0(multihash) => require!(
+                multihash[..2] == [0x12, 0x20],
+                ErrorCode::InvalidDocumentRef
+            ),
+            DocumentRef::CidV1(cid) => require!(
+                cid.len() >= 4 && cid.len() <= Self::MAX_CID_V1_LEN && cid[0] == 0x01,
+                ErrorCode::InvalidDocumentRef
+            ),
+            DocumentRef::Sha256(_) => {}
+        }
+        Ok(())
+    }
+
+    /// SHA-256 over the variant tag and its bytes, identifying the reference on-chain.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let (tag, bytes): (u8, &[u8]) = match self {
+            DocumentRef::CidV0(multihash) => (0, multihash),
+            DocumentRef::CidV1(cid) => (1, cid),
+            DocumentRef::Sha256(digest) => (2, digest),
+        };
+        hashv(&[&[tag], bytes]).to_bytes()
+    }
+}
+
 #[account]
 pub struct UserReputation {
     pub wallet: Pubkey,
@@ -813,6 +4681,7 @@ pub struct UserReputation {
     pub quality_ratings_sum: u32,          // Sum of quality ratings (1-5 each)
     pub quality_ratings_count: u32,        // Number of quality ratings received
     pub dispute_count_vendor: u32,
+    pub missed_deadlines_vendor: u32,      // Milestones that expired before delivery
     
     // Client reputation (when buying services/paying)
     pub client_score: u32,              // 0-500 (represents 0.0-5.0, stored as fixed-point * 100)
@@ -825,10 +4694,11 @@ pub struct UserReputation {
     pub dispute_count_client: u32,
     
     // Cross-role metrics
-    pub total_value_transacted: u64,
+    pub total_value_transacted: u64,       // Native SOL volume in lamports
     pub first_activity: i64,
     pub last_activity: i64,
     pub bump: u8,
+    pub token_volumes: Vec<TokenVolume>,   // Volume per SPL mint
 }
 
 impl UserReputation {
@@ -848,6 +4718,7 @@ impl UserReputation {
         4 +  // quality_ratings_sum
         4 +  // quality_ratings_count
         4 +  // dispute_count_vendor
+        4 +  // missed_deadlines_vendor
         // Client reputation
         4 +  // client_score
         4 +  // deals_as_client
@@ -861,13 +4732,61 @@ impl UserReputation {
         8 +  // total_value_transacted
         8 +  // first_activity
         8 +  // last_activity
-        1;   // bump
+        1 +  // bump
+        (4 + TokenVolume::LEN * Self::MAX_TRACKED_MINTS); // token_volumes vec
+
+    pub const MAX_TRACKED_MINTS: usize = 8;
+
+    /// Counts a new contract deal under the participant's role.
+    pub fn record_deal_as(&mut self, role: ParticipantRole) {
+        match role {
+            ParticipantRole::Vendor => self.deals_as_vendor += 1,
+            ParticipantRole::Client => self.deals_as_client += 1,
+            ParticipantRole::Reviewer | ParticipantRole::Observer => {}
+        }
+    }
+
+    /// Reverses `record_deal_as` when an approval is revoked.
+    pub fn forget_deal_as(&mut self, role: ParticipantRole) {
+        match role {
+            ParticipantRole::Vendor => self.deals_as_vendor = self.deals_as_vendor.saturating_sub(1),
+            ParticipantRole::Client => self.deals_as_client = self.deals_as_client.saturating_sub(1),
+            ParticipantRole::Reviewer | ParticipantRole::Observer => {}
+        }
+    }
+
+    /// Adds `amount` to the transacted volume for `mint`, where `None` is native SOL. Once
+    /// `MAX_TRACKED_MINTS` mints are tracked, volume in a new mint goes unrecorded rather
+    /// than failing the payout it belongs to.
+    pub fn record_value_transacted(&mut self, mint: Option<Pubkey>, amount: u64) {
+        let Some(mint) = mint else {
+            self.total_value_transacted += amount;
+            return;
+        };
+
+        if let Some(volume) = self.token_volumes.iter_mut().find(|v| v.mint == mint) {
+            volume.amount += amount;
+        } else if self.token_volumes.len() < Self::MAX_TRACKED_MINTS {
+            self.token_volumes.push(TokenVolume { mint, amount });
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct TokenVolume {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+impl TokenVolume {
+    pub const LEN: usize = 32 + 8;
 }
 
 #[account]
 pub struct EscrowMilestone {
     pub milestone_id: u64,
     pub contract_id: u64,
+    pub contract: Pubkey,     // Parent contract account, part of the PDA seeds; fixed offset for memcmp filters
     pub description: String,
     pub amount: u64,
     pub recipient: Pubkey,
@@ -879,6 +4798,20 @@ pub struct EscrowMilestone {
     pub creator: Pubkey,
     pub created_at: i64,
     pub bump: u8,
+    pub mint: Option<Pubkey>, // None for native SOL milestones
+    pub amount_delivered: u64, // What recipients received, net of Token-2022 transfer fees
+    pub released_amount: u64,  // Portion of `amount` settled so far, including late penalties
+    pub late_penalty: Option<LatePenalty>,
+    pub completed_at: Option<i64>,
+    pub penalty_amount: u64,   // Late penalties refunded to the creator
+    pub early_bonus: Option<EarlyBonus>, // Locked on top of `amount`
+    pub retention: Option<Retention>,
+    pub retained_amount: u64,  // Held back from payouts until the warranty period ends
+    pub settled_at: Option<i64>, // When the milestone was released, cancelled or expired
+    pub disputed: bool,
+    pub splits: Vec<RecipientShare>, // Empty when `recipient` receives everything
+    pub amendment_count: u32, // Bumped each time an approved amendment is applied
+    pub dispute_count: u32,   // Disputes opened so far; seeds the next one
 }
 
 impl EscrowMilestone {
@@ -886,6 +4819,7 @@ impl EscrowMilestone {
     pub const LEN: usize = 8 + // discriminator
         8 + // milestone_id
         8 + // contract_id
+        32 + // contract
         (4 + 200) + // description (max 200 chars)
         8 + // amount
         32 + // recipient
@@ -896,14 +4830,593 @@ impl EscrowMilestone {
         (1 + 32) + // marked_complete_by option
         32 + // creator
         8 + // created_at
+        1 + // bump
+        (1 + 32) + // mint option
+        8 + // amount_delivered
+        8 + // released_amount
+        (1 + LatePenalty::LEN) + // late_penalty option
+        (1 + 8) + // completed_at option
+        8 + // penalty_amount
+        (1 + EarlyBonus::LEN) + // early_bonus option
+        (1 + Retention::LEN) + // retention option
+        8 + // retained_amount
+        (1 + 8) + // settled_at option
+        1 + // disputed
+        (4 + RecipientShare::LEN * Self::MAX_SPLITS) + // splits vec
+        4 + // amendment_count
+        4; // dispute_count
+
+    pub const MAX_SPLITS: usize = 5;
+
+    /// Everyone this milestone pays: the split wallets, or just `recipient` without splits.
+    pub fn recipients(&self) -> Vec<Pubkey> {
+        if self.splits.is_empty() {
+            vec![self.recipient]
+        } else {
+            self.splits.iter().map(|share| share.wallet).collect()
+        }
+    }
+
+    /// Whether `wallet` is paid by this milestone, as the lead recipient or through a split.
+    pub fn is_recipient(&self, wallet: &Pubkey) -> bool {
+        self.recipient == *wallet || self.splits.iter().any(|share| share.wallet == *wallet)
+    }
+
+    /// Escrowed funds not yet paid out, excluding any early-delivery bonus.
+    pub fn remaining_amount(&self) -> u64 {
+        self.amount - self.released_amount
+    }
+
+    pub fn bonus_amount(&self) -> u64 {
+        self.early_bonus.as_ref().map_or(0, |bonus| bonus.amount)
+    }
+
+    pub fn warranty_ends_at(&self) -> Option<i64> {
+        let warranty_seconds = self.retention.as_ref().map_or(0, |retention| retention.warranty_seconds);
+        self.settled_at.map(|settled_at| settled_at.saturating_add(warranty_seconds))
+    }
+
+    /// Splits a release of `amount` between recipient and creator, holding back retention
+    /// from the recipient's share. The bonus is settled on the final release: earned if that
+    /// delivery was marked complete by the early-bird time.
+    pub fn settle(&self, amount: u64, is_final: bool) -> Settlement {
+        let penalty = self.late_penalty_for(amount);
+        let retention_bps = self.retention.as_ref().map_or(0, |retention| retention.bps);
+        let retained = ((amount - penalty) as u128 * retention_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        let (bonus, forfeited_bonus) = match (&self.early_bonus, self.completed_at) {
+            (Some(early_bonus), Some(completed_at)) if is_final && completed_at <= early_bonus.early_bird_ts => {
+                (early_bonus.amount, 0)
+            }
+            (Some(early_bonus), _) if is_final => (0, early_bonus.amount),
+            _ => (0, 0),
+        };
+        Settlement {
+            to_recipient: amount - penalty - retained + bonus,
+            to_creator: penalty + forfeited_bonus,
+            penalty,
+            bonus,
+            retained,
+        }
+    }
+
+    /// Share of `payout` forfeited for marking the milestone complete after the deadline
+    /// and grace period. Every started day late costs `bps_per_day`, up to `max_bps`.
+    pub fn late_penalty_for(&self, payout: u64) -> u64 {
+        let (Some(penalty), Some(completed_at)) = (&self.late_penalty, self.completed_at) else {
+            return 0;
+        };
+        let late_by = completed_at
+            .saturating_sub(self.deadline)
+            .saturating_sub(penalty.grace_period_seconds);
+        if late_by <= 0 {
+            return 0;
+        }
+        let days_late = (late_by as u64).div_ceil(SECONDS_PER_DAY);
+        let bps = days_late
+            .saturating_mul(penalty.bps_per_day as u64)
+            .min(penalty.max_bps as u64);
+        (payout as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
+
+/// A co-recipient's cut of every payout from a split milestone.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct RecipientShare {
+    pub wallet: Pubkey,
+    pub share_bps: u16,
+}
+
+impl RecipientShare {
+    pub const LEN: usize = 32 + 2;
+}
+
+/// A pending change to a funded escrow milestone, applied once enough participants approve.
+#[account]
+pub struct MilestoneAmendment {
+    pub escrow_milestone: Pubkey,
+    pub proposer: Pubkey,
+    pub new_amount: Option<u64>,
+    pub new_deadline: Option<i64>,
+    pub new_description: Option<String>,
+    pub approvals_required: u8,
+    pub approvals: Vec<Pubkey>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl MilestoneAmendment {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // escrow_milestone
+        32 + // proposer
+        (1 + 8) + // new_amount option
+        (1 + 8) + // new_deadline option
+        (1 + 4 + 200) + // new_description option (max 200 chars)
+        1 + // approvals_required
+        (4 + 32 * EscrowMilestone::MAX_PARTICIPANTS) + // approvals vec
+        8 + // created_at
+        1; // bump
+}
+
+/// Proof that `rater` already rated `ratee` for a released milestone.
+#[account]
+pub struct RatingReceipt {
+    pub escrow_milestone: Pubkey,
+    pub rater: Pubkey,
+    pub ratee: Pubkey,
+    pub rating: u8,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl RatingReceipt {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // escrow_milestone
+        32 + // rater
+        32 + // ratee
+        1 + // rating
+        8 + // created_at
+        1; // bump
+}
+
+/// A disagreement over a milestone's escrowed funds, settled by an arbiter panel vote.
+#[account]
+pub struct Dispute {
+    pub escrow_milestone: Pubkey,
+    pub claimant: Pubkey,
+    pub respondent: Pubkey,
+    pub evidence_hash: [u8; 32],     // Hash of the off-chain evidence bundle
+    pub status: DisputeStatus,
+    pub recipient_bps: Option<u16>,  // Share of the held funds ruled to the recipients
+    pub opened_at: i64,
+    pub resolved_at: Option<i64>,
+    pub bump: u8,
+    pub evidence_deadline: i64,      // Last moment evidence can be submitted; votes come after
+    pub panel: Vec<Pubkey>,          // Arbiters voting in the current round
+    pub votes: Vec<PanelVote>,
+    pub round: u8,                   // 0 for the first ruling, 1 once appealed
+    pub ruled_at: Option<i64>,
+    pub first_ruling_bps: Option<u16>, // The appealed ruling
+    pub appellant: Option<Pubkey>,
+    pub appeal_fee: u64,             // Lamports posted into the escrow milestone account
+    pub fee_pool: u64,               // Lamports held here to pay arbiters as they vote
+    pub first_round_votes: Vec<PanelVote>, // Kept after an appeal to record overturned rulings
+    pub resolution_deadline: i64,    // After this a stalled round falls back to a default outcome
+    pub appeal_window_seconds: i64,  // Registry's appeal window when the dispute was opened
+    pub index: u32,                  // The milestone's dispute count when opened, part of the PDA seeds
+}
+
+impl Dispute {
+    pub const EVIDENCE_PERIOD_SECONDS: i64 = 3 * SECONDS_PER_DAY as i64;
+    pub const APPEAL_WINDOW_SECONDS: i64 = 2 * SECONDS_PER_DAY as i64;
+    pub const RESOLUTION_PERIOD_SECONDS: i64 = 30 * SECONDS_PER_DAY as i64;
+    pub const MAX_PANEL: usize = 9;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // escrow_milestone
+        32 + // claimant
+        32 + // respondent
+        32 + // evidence_hash
+        1 + // status enum
+        (1 + 2) + // recipient_bps option
+        8 + // opened_at
+        (1 + 8) + // resolved_at option
+        1 + // bump
+        8 + // evidence_deadline
+        (4 + 32 * Self::MAX_PANEL) + // panel vec
+        (4 + PanelVote::LEN * Self::MAX_PANEL) + // votes vec
+        1 + // round
+        (1 + 8) + // ruled_at option
+        (1 + 2) + // first_ruling_bps option
+        (1 + 32) + // appellant option
+        8 + // appeal_fee
+        8 + // fee_pool
+        (4 + PanelVote::LEN * Self::MAX_PANEL) + // first_round_votes vec
+        8 + // resolution_deadline
+        8 + // appeal_window_seconds
+        4; // index
+
+    pub fn appeal_deadline(&self) -> i64 {
+        self.ruled_at.unwrap_or(i64::MAX).saturating_add(self.appeal_window_seconds)
+    }
+
+    /// The recipients' share the panel has settled on, if it has: a strict majority for one
+    /// share, or the median once every member has voted.
+    pub fn tally(&self) -> Option<u16> {
+        for vote in &self.votes {
+            let backing = self.votes.iter().filter(|other| other.recipient_bps == vote.recipient_bps).count();
+            if backing * 2 > self.panel.len() {
+                return Some(vote.recipient_bps);
+            }
+        }
+        if self.panel.is_empty() || self.votes.len() < self.panel.len() {
+            return None;
+        }
+        let mut shares: Vec<u16> = self.votes.iter().map(|vote| vote.recipient_bps).collect();
+        shares.sort_unstable();
+        Some(shares[shares.len() / 2])
+    }
+
+    /// Who gets a posted appeal fee back: the appellant if the appeal moved the ruling their
+    /// way, otherwise the other party.
+    pub fn appeal_fee_recipient(&self, creator: &Pubkey) -> Option<Pubkey> {
+        let appellant = self.appellant?;
+        if self.appeal_fee == 0 {
+            return None;
+        }
+        let (first, last) = (self.first_ruling_bps?, self.recipient_bps?);
+        // The creator wants a smaller share for the recipients, the recipients a larger one
+        let appeal_won = if appellant == *creator { last < first } else { last > first };
+        let other_party = if appellant == self.claimant { self.respondent } else { self.claimant };
+        Some(if appeal_won { appellant } else { other_party })
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PanelVote {
+    pub arbiter: Pubkey,
+    pub recipient_bps: u16,
+}
+
+impl PanelVote {
+    pub const LEN: usize = 32 + 2;
+}
+
+/// Program-wide arbitration config: who can seat panels and slash arbiters, and what
+/// arbiters must stake to be eligible.
+#[account]
+pub struct ArbiterRegistry {
+    pub authority: Pubkey,
+    pub appeal_fee: u64, // Lamports an appellant posts to appeal a ruling
+    pub bump: u8,
+    pub min_stake: u64,
+    pub stake_mint: Option<Pubkey>, // None when arbiters stake SOL
+    pub dispute_timings: DisputeTimings,
+}
+
+impl ArbiterRegistry {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority
+        8 + // appeal_fee
+        1 + // bump
+        8 + // min_stake
+        (1 + 32) + // stake_mint option
+        DisputeTimings::LEN; // dispute_timings
+}
+
+/// How long each dispute phase lasts, set on the registry.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct DisputeTimings {
+    pub evidence_seconds: i64,
+    pub appeal_window_seconds: i64,
+    pub resolution_seconds: i64, // Per round, from opening or appeal
+}
+
+impl DisputeTimings {
+    pub const LEN: usize = 8 + 8 + 8;
+    pub const DEFAULT: Self = Self {
+        evidence_seconds: Dispute::EVIDENCE_PERIOD_SECONDS,
+        appeal_window_seconds: Dispute::APPEAL_WINDOW_SECONDS,
+        resolution_seconds: Dispute::RESOLUTION_PERIOD_SECONDS,
+    };
+
+    /// A round has to outlast its evidence period, or it could stall before anyone votes.
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.evidence_seconds > 0
+                && self.appeal_window_seconds > 0
+                && self.resolution_seconds > self.evidence_seconds,
+            ErrorCode::InvalidDisputeTimings
+        );
+        Ok(())
+    }
+}
+
+/// A staked arbiter and its track record. SOL stake sits on this account; token stake in
+/// its vault.
+#[account]
+pub struct Arbiter {
+    pub wallet: Pubkey,
+    pub stake_mint: Option<Pubkey>,
+    pub stake: u64,
+    pub fee: u64,                    // Lamports paid from a dispute's fee pool per vote
+    pub active: bool,
+    pub deactivated_at: Option<i64>,
+    pub rulings_issued: u32,         // Panel rulings the arbiter voted for
+    pub rulings_overturned: u32,
+    pub slashed_amount: u64,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl Arbiter {
+    pub const UNBONDING_SECONDS: i64 = 7 * SECONDS_PER_DAY as i64;
+    pub const LEN: usize = 8 + // discriminator
+        32 + // wallet
+        (1 + 32) + // stake_mint option
+        8 + // stake
+        8 + // fee
+        1 + // active
+        (1 + 8) + // deactivated_at option
+        4 + // rulings_issued
+        4 + // rulings_overturned
+        8 + // slashed_amount
+        8 + // registered_at
+        1; // bump
+
+    pub fn new(wallet: Pubkey, stake_mint: Option<Pubkey>, fee: u64, stake: u64, bump: u8) -> Result<Self> {
+        Ok(Self {
+            wallet,
+            stake_mint,
+            stake,
+            fee,
+            active: true,
+            deactivated_at: None,
+            rulings_issued: 0,
+            rulings_overturned: 0,
+            slashed_amount: 0,
+            registered_at: Clock::get()?.unix_timestamp,
+            bump,
+        })
+    }
+
+    /// Whether the arbiter can be seated on new panels.
+    pub fn is_eligible(&self, registry: &ArbiterRegistry) -> bool {
+        self.active && self.stake >= registry.min_stake && self.stake_mint == registry.stake_mint
+    }
+}
+
+impl EscrowFunds for Arbiter {
+    fn funds_mint(&self) -> Option<Pubkey> {
+        self.stake_mint
+    }
+
+    fn with_signer_seeds<R>(&self, f: impl FnOnce(&[&[&[u8]]]) -> R) -> R {
+        f(&[&[b"arbiter", self.wallet.as_ref(), &[self.bump]]])
+    }
+}
+
+/// Append-only record of the evidence submitted to a dispute.
+#[account]
+pub struct EvidenceLog {
+    pub dispute: Pubkey,
+    pub entries: Vec<EvidenceEntry>,
+    pub bump: u8,
+}
+
+impl EvidenceLog {
+    pub const MAX_ENTRIES: usize = 20;
+    pub const LEN: usize = 8 + // discriminator
+        32 + // dispute
+        (4 + EvidenceEntry::LEN * Self::MAX_ENTRIES) + // entries vec
         1; // bump
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EvidenceEntry {
+    pub submitter: Pubkey,
+    pub document: DocumentRef,
+    pub label: String,
+    pub submitted_at: i64,
+}
+
+impl EvidenceEntry {
+    pub const MAX_LABEL_LEN: usize = 32;
+    pub const LEN: usize = 32 + // submitter
+        DocumentRef::LEN + // document
+        (4 + Self::MAX_LABEL_LEN) + // label
+        8; // submitted_at
+}
+
+/// Where one release out of an escrow milestone goes.
+pub struct Settlement {
+    pub to_recipient: u64,
+    pub to_creator: u64,
+    pub penalty: u64,
+    pub bonus: u64,
+    pub retained: u64,
+}
+
+/// Share of each payout held back until `warranty_seconds` after the milestone settles.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct Retention {
+    pub bps: u16,
+    pub warranty_seconds: i64,
+}
+
+impl Retention {
+    pub const LEN: usize = 2 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.bps as u64 <= BPS_DENOMINATOR && self.warranty_seconds >= 0,
+            ErrorCode::InvalidRetention
+        );
+        Ok(())
+    }
+}
+
+/// Extra funds paid to the recipient only for delivery marked complete by `early_bird_ts`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct EarlyBonus {
+    pub amount: u64,
+    pub early_bird_ts: i64,
+}
+
+impl EarlyBonus {
+    pub const LEN: usize = 8 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.amount > 0, ErrorCode::InvalidAmount);
+        Ok(())
+    }
+}
+
+/// Liquidated-damages schedule applied to payouts for late delivery.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct LatePenalty {
+    pub grace_period_seconds: i64,
+    pub bps_per_day: u16,
+    pub max_bps: u16,
+}
+
+impl LatePenalty {
+    pub const LEN: usize = 8 + 2 + 2;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.grace_period_seconds >= 0 && self.max_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidPenaltySchedule
+        );
+        Ok(())
+    }
+}
+
+/// Program-owned account that holds milestone funds, either as its own lamports or in a
+/// token vault it signs for.
+pub trait EscrowFunds {
+    fn funds_mint(&self) -> Option<Pubkey>;
+    fn with_signer_seeds<R>(&self, f: impl FnOnce(&[&[&[u8]]]) -> R) -> R;
+}
+
+impl EscrowFunds for EscrowMilestone {
+    fn funds_mint(&self) -> Option<Pubkey> {
+        self.mint
+    }
+
+    fn with_signer_seeds<R>(&self, f: impl FnOnce(&[&[&[u8]]]) -> R) -> R {
+        let milestone_id = self.milestone_id.to_le_bytes();
+        f(&[&[b"escrow", self.contract.as_ref(), &milestone_id, &[self.bump]]])
+    }
+}
+
+#[account]
+pub struct VestingMilestone {
+    pub milestone_id: u64,
+    pub contract_id: u64,
+    pub description: String,
+    pub amount: u64,               // Total to vest; cut to the vested amount on cancel
+    pub claimed_amount: u64,
+    pub amount_delivered: u64,     // What the recipient received, net of Token-2022 transfer fees
+    pub recipient: Pubkey,
+    pub creator: Pubkey,
+    pub schedule: VestingSchedule,
+    pub status: VestingStatus,
+    pub cancelled_at: Option<i64>,
+    pub mint: Option<Pubkey>,      // None for native SOL milestones
+    pub created_at: i64,
+    pub bump: u8,
+    pub contract: Pubkey,          // Parent contract account, part of the PDA seeds
+}
+
+impl VestingMilestone {
+    pub const LEN: usize = 8 + // discriminator
+        8 + // milestone_id
+        8 + // contract_id
+        (4 + 200) + // description (max 200 chars)
+        8 + // amount
+        8 + // claimed_amount
+        8 + // amount_delivered
+        32 + // recipient
+        32 + // creator
+        VestingSchedule::LEN + // schedule
+        1 + // status enum
+        (1 + 8) + // cancelled_at option
+        (1 + 32) + // mint option
+        8 + // created_at
+        1 + // bump
+        32; // contract
+
+    /// Amount unlocked at `now`. Once cancelled, `amount` already equals what had vested.
+    pub fn vested_amount(&self, now: i64) -> u64 {
+        if self.cancelled_at.is_some() {
+            return self.amount;
+        }
+        let schedule = &self.schedule;
+        if now < schedule.cliff_ts.unwrap_or(schedule.start_ts) {
+            return 0;
+        }
+        if now >= schedule.end_ts {
+            return self.amount;
+        }
+        let elapsed = (now - schedule.start_ts) as u128;
+        let duration = (schedule.end_ts - schedule.start_ts) as u128;
+        (self.amount as u128 * elapsed / duration) as u64
+    }
+
+    pub fn claimable_amount(&self, now: i64) -> u64 {
+        self.vested_amount(now) - self.claimed_amount
+    }
+}
+
+impl EscrowFunds for VestingMilestone {
+    fn funds_mint(&self) -> Option<Pubkey> {
+        self.mint
+    }
+
+    fn with_signer_seeds<R>(&self, f: impl FnOnce(&[&[&[u8]]]) -> R) -> R {
+        let milestone_id = self.milestone_id.to_le_bytes();
+        f(&[&[b"vesting", self.contract.as_ref(), &milestone_id, &[self.bump]]])
+    }
+}
+
+/// Linear unlock between `start_ts` and `end_ts`. Nothing unlocks before `cliff_ts`, after
+/// which everything accrued since `start_ts` becomes claimable at once.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct VestingSchedule {
+    pub start_ts: i64,
+    pub cliff_ts: Option<i64>,
+    pub end_ts: i64,
+}
+
+impl VestingSchedule {
+    pub const LEN: usize = 8 + (1 + 8) + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.start_ts < self.end_ts, ErrorCode::InvalidVestingSchedule);
+        if let Some(cliff_ts) = self.cliff_ts {
+            require!(
+                cliff_ts >= self.start_ts && cliff_ts <= self.end_ts,
+                ErrorCode::InvalidVestingSchedule
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum VestingStatus {
+    Active,
+    Cancelled,
+    Completed,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
 pub enum ContractStatus {
     Active,
     Completed,
     Cancelled,
+    Rejected,
+    Expired,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
@@ -913,6 +5426,17 @@ pub enum MilestoneStatus {
     MarkedComplete,
     Released,
     Cancelled,
+    Expired,
+    Resolved, // Settled by an arbiter ruling
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum DisputeStatus {
+    Open,     // Collecting evidence, then panel votes
+    Ruled,    // Awaiting appeal or execution
+    Appealed, // Awaiting a larger panel
+    Resolved, // Ruling executed
+    Lapsed,   // Timed out without a ruling; the milestone was unfrozen
 }
 
 #[error_code]
@@ -939,6 +5463,7 @@ pub enum ErrorCode {
     DescriptionTooLong,
     #[msg("Only creator can initialize escrow")]
     OnlyCreatorCanInitializeEscrow,
+    /// Deprecated: replaced by `RecipientNotVendor`, kept so later error codes keep their numbers
     #[msg("Recipient must be a participant")]
     RecipientNotParticipant,
     #[msg("Invalid amount")]
@@ -955,11 +5480,194 @@ pub enum ErrorCode {
     OnlyCreatorCanCancelEscrow,
     #[msg("Cannot cancel milestone in current status")]
     CannotCancelMilestone,
+    /// Deprecated: replaced by `InvalidDocumentRef`, kept so later error codes keep their numbers
     #[msg("IPFS hash too long (max 46 characters)")]
     IpfsHashTooLong,
     #[msg("Invalid rating (must be between 1 and 5)")]
     InvalidRating,
     #[msg("Only recipient can mark milestone as complete")]
     OnlyRecipientCanMarkComplete,
+    #[msg("Too many mints tracked on this reputation account")]
+    TooManyTrackedMints,
+    #[msg("Token accounts are required for SPL milestones")]
+    MissingTokenAccounts,
+    #[msg("Destination account does not belong to the expected wallet")]
+    InvalidDestination,
+    #[msg("Mint does not match the escrow milestone")]
+    InvalidMint,
+    #[msg("Mint uses a Token-2022 extension escrow does not support")]
+    UnsupportedMintExtension,
+    #[msg("Only creator can release part of an escrow")]
+    OnlyCreatorCanReleasePartial,
+    #[msg("Vesting schedule must start before it ends, with any cliff in between")]
+    InvalidVestingSchedule,
+    #[msg("Only recipient can claim vested funds")]
+    OnlyRecipientCanClaim,
+    #[msg("Vesting milestone is fully paid out")]
+    VestingCompleted,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Milestone deadline has not passed yet")]
+    DeadlineNotReached,
+    #[msg("Invalid late penalty schedule")]
+    InvalidPenaltySchedule,
+    #[msg("Invalid retention terms")]
+    InvalidRetention,
+    #[msg("No retention is held on this milestone")]
+    NoRetentionHeld,
+    #[msg("Milestone is under dispute")]
+    MilestoneDisputed,
+    #[msg("Milestone has not been settled yet")]
+    MilestoneNotSettled,
+    #[msg("Warranty period has not ended")]
+    WarrantyPeriodActive,
+    #[msg("Warranty period has already ended")]
+    WarrantyPeriodEnded,
+    #[msg("Too many recipient splits (max 5)")]
+    TooManySplits,
+    #[msg("Splits must be unique, include the recipient and sum to 10000 basis points")]
+    InvalidSplits,
+    #[msg("Missing destination or reputation account for a split recipient")]
+    MissingRecipientAccounts,
+    #[msg("Reputation account does not belong to the split recipient")]
+    InvalidRecipientReputation,
+    #[msg("Amendment does not change anything")]
+    EmptyAmendment,
+    #[msg("Deadline must be in the future")]
+    InvalidDeadline,
+    #[msg("Already approved this amendment")]
+    AlreadyApprovedAmendment,
+    #[msg("Only creator can apply an amendment")]
+    OnlyCreatorCanApplyAmendment,
+    #[msg("Only the proposer can withdraw an amendment")]
+    OnlyProposerCanWithdraw,
+    #[msg("Account does not belong to this contract")]
+    ContractMismatch,
+    #[msg("Milestone has not been released")]
+    MilestoneNotReleased,
+    #[msg("Only the other party to a released milestone can leave this rating")]
+    NotACounterparty,
+    #[msg("Arbiter cannot be a contract participant")]
+    ArbiterIsParticipant,
+    #[msg("Only the milestone's creator or recipients can open a dispute")]
+    OnlyPartiesCanDispute,
+    #[msg("Milestone holds no funds to dispute")]
+    NoFundsInEscrow,
+    #[msg("Dispute is not open")]
+    DisputeNotOpen,
+    #[msg("Ruling share must be at most 10000 basis points")]
+    InvalidRuling,
+    #[msg("Only the claimant or respondent can submit evidence")]
+    NotADisputeParty,
+    #[msg("Evidence deadline has passed")]
+    EvidencePeriodEnded,
+    #[msg("Evidence can still be submitted to this dispute")]
+    EvidencePeriodActive,
+    #[msg("Evidence label too long (max 32 characters)")]
+    EvidenceLabelTooLong,
+    /// Deprecated: evidence documents are checked by `InvalidDocumentRef`, kept so later error
+    /// codes keep their numbers
+    #[msg("Evidence CID must be 1 to 64 characters")]
+    InvalidEvidenceDocument,
+    #[msg("Evidence log is full")]
+    EvidenceLogFull,
+    #[msg("Only the registry authority can do this")]
+    OnlyRegistryAuthority,
+    #[msg("Arbiter is not active with the minimum stake")]
+    ArbiterNotEligible,
+    #[msg("Panel must be an odd number of distinct, neutral arbiters (max 9)")]
+    InvalidPanel,
+    #[msg("Panel has already started voting")]
+    PanelAlreadyVoting,
+    #[msg("Appeal panel must be larger than the panel that ruled")]
+    AppealPanelTooSmall,
+    #[msg("You are not on this dispute's panel")]
+    NotOnPanel,
+    #[msg("Already voted on this dispute")]
+    AlreadyVoted,
+    #[msg("Dispute has not been ruled on")]
+    DisputeNotRuled,
+    #[msg("Ruling has already been appealed")]
+    AlreadyAppealed,
+    #[msg("Appeal window has closed")]
+    AppealWindowClosed,
+    #[msg("Ruling can still be appealed")]
+    AppealWindowOpen,
+    #[msg("Dispute fee pool cannot cover the arbiter's fee")]
+    InsufficientDisputeFees,
+    #[msg("Arbiter must be inactive to withdraw stake")]
+    ArbiterStillActive,
+    #[msg("Stake is still unbonding")]
+    StakeUnbonding,
+    #[msg("Arbiter's first-round vote was not overturned")]
+    RulingNotOverturned,
+    #[msg("Contract document is already set; propose an amendment instead")]
+    DocumentAlreadySet,
+    #[msg("Contract has no document to amend yet")]
+    NoDocumentToAmend,
+    #[msg("Amendment is not pending")]
+    AmendmentNotPending,
+    #[msg("Amendment does not build on the contract's current version")]
+    StaleAmendment,
+    #[msg("Already a participant")]
+    AlreadyAParticipant,
+    #[msg("Observers cannot approve")]
+    ObserverCannotApprove,
+    #[msg("Participants who can approve need a non-zero weight")]
+    InvalidParticipantWeight,
+    #[msg("Veto threshold must be between 1 and the number of approving participants")]
+    InvalidVetoThreshold,
+    #[msg("You have already rejected this contract")]
+    AlreadyRejected,
+    #[msg("You have not approved this contract")]
+    NotApproved,
+    #[msg("Approval deadline has passed")]
+    ApprovalDeadlinePassed,
+    #[msg("Approval deadline has not passed")]
+    ApprovalDeadlineNotPassed,
+    #[msg("Missing or invalid approver reputation account")]
+    InvalidApproverReputation,
+    #[msg("Only the creator can close this account")]
+    OnlyCreatorCanClose,
+    #[msg("Only expired, cancelled or rejected contracts can be closed")]
+    ContractStillOpen,
+    #[msg("Contract has funded milestones")]
+    ContractHasMilestones,
+    #[msg("Signed out of turn: an earlier participant in the signing order must approve first")]
+    SignedOutOfTurn,
+    #[msg("Contract has no document to approve yet")]
+    NoDocumentToApprove,
+    #[msg("Document hash does not match the contract's current document")]
+    DocumentHashMismatch,
+    #[msg("Amendment was proposed against an earlier milestone at this address")]
+    StaleMilestoneAmendment,
+    #[msg("Milestone has already paid out part of its funds")]
+    MilestonePartiallyReleased,
+    #[msg("The milestone creator cannot be one of its recipients")]
+    CreatorCannotBeRecipient,
+    #[msg("You cannot rate yourself")]
+    CannotRateSelf,
+    #[msg("Only the program's upgrade authority can do this")]
+    NotUpgradeAuthority,
+    #[msg("Dispute is still within its resolution period")]
+    DisputeNotStalled,
+    #[msg("Invalid document reference")]
+    InvalidDocumentRef,
+    #[msg("Recipient must be a vendor on the contract")]
+    RecipientNotVendor,
+    #[msg("This contract id was used by a closed contract that left amendments behind")]
+    ContractIdReused,
+    #[msg("Dispute periods must be positive, and a round must outlast its evidence period")]
+    InvalidDisputeTimings,
+    #[msg("Participant is still the recipient of an unsettled escrow milestone")]
+    RecipientHasOpenMilestones,
+    #[msg("Milestone still holds retention from an earlier payout")]
+    RetentionStillHeld,
+    #[msg("Only cancelled or expired milestones can be closed")]
+    MilestoneNotClosable,
+    #[msg("Arbiter accounts of the ruling's earlier voters are missing or out of order")]
+    InvalidVoterArbiter,
+    #[msg("Contract still has pending amendments or participant changes")]
+    ContractHasPendingProposals,
 }
 
@@ -1,8 +1,44 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { AgreedContracts } from "../target/types/agreed_contracts";
-import { PublicKey, Keypair } from "@solana/web3.js";
+import { PublicKey, Keypair, SystemProgram, Transaction, TransactionInstruction } from "@solana/web3.js";
+import {
+  createAccount,
+  createInitializeMintInstruction,
+  createInitializeNonTransferableMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createMint,
+  ExtensionType,
+  getAccount,
+  getMintLen,
+  mintTo,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
 import { assert } from "chai";
+import { createHash } from "crypto";
+
+// Raw SHA-256 document reference, checkable against a local file without IPFS
+const sha256Document = (content: string) => ({
+  sha256: { 0: Array.from(createHash("sha256").update(content).digest()) },
+});
+
+// Mirrors DocumentRef::fingerprint: SHA-256 over the variant tag and the reference bytes
+const documentFingerprint = (tag: number, bytes: Uint8Array) =>
+  Array.from(createHash("sha256").update(Buffer.from([tag])).update(bytes).digest());
+const sha256Fingerprint = (content: string) =>
+  documentFingerprint(2, createHash("sha256").update(content).digest());
+
+// A CIDv0 is a base58btc sha2-256 multihash; the matching dag-pb CIDv1 prefixes it with
+// the version and codec varints
+const termsCid = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+const termsMultihash = Buffer.from(anchor.utils.bytes.bs58.decode(termsCid));
+const termsCidV1 = Buffer.concat([Buffer.from([0x01, 0x70]), termsMultihash]);
+
+// Contract participants carry an explicit role and approval weight
+const asVendor = (wallet: PublicKey, weight = 1) => ({ wallet, role: { vendor: {} }, weight });
+const asClient = (wallet: PublicKey, weight = 1) => ({ wallet, role: { client: {} }, weight });
+const asObserver = (wallet: PublicKey) => ({ wallet, role: { observer: {} }, weight: 0 });
 
 describe("agreed_contracts", () => {
   const provider = anchor.AnchorProvider.env();
@@ -11,6 +47,7 @@ describe("agreed_contracts", () => {
   const program = anchor.workspace.AgreedContracts as Program<AgreedContracts>;
   
   const creator = provider.wallet.publicKey;
+  const payer = (provider.wallet as anchor.Wallet).payer;
   const participant1 = Keypair.generate();
   const participant2 = Keypair.generate();
   
@@ -19,6 +56,288 @@ describe("agreed_contracts", () => {
   let contractPDA: PublicKey;
   let creatorRepPDA: PublicKey;
 
+  // Approvals name the document they approve, so contracts need one first
+  const termsHash = sha256Fingerprint("agreed terms");
+  const setTermsDocument = (contract: PublicKey) =>
+    program.methods
+      .setContractDocument(sha256Document("agreed terms"))
+      .accounts({ contract, updater: creator })
+      .rpc();
+
+  const contractAddress = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("contract"), new anchor.BN(id).toArrayLike(Buffer, "le", 8), creator.toBuffer()],
+      program.programId
+    )[0];
+  const amendmentAddress = (contract: PublicKey, version: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("amendment"), contract.toBuffer(), new anchor.BN(version).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    )[0];
+
+  const reputationAddress = (wallet: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("reputation"), wallet.toBuffer()], program.programId)[0];
+
+  // A fresh keypair paid for by the provider wallet, with its reputation account set up
+  const fundedWallet = async () => {
+    const wallet = Keypair.generate();
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: creator,
+          toPubkey: wallet.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      )
+    );
+    await program.methods
+      .initializeReputation()
+      .accounts({
+        reputation: reputationAddress(wallet.publicKey),
+        user: wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+    return wallet;
+  };
+
+  // Deadlines are checked against the cluster clock, which can trail the local one
+  const chainTime = async () =>
+    provider.connection.getBlockTime(await provider.connection.getSlot("confirmed"));
+  const waitForChainTime = async (timestamp: number) => {
+    while ((await chainTime()) <= timestamp) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+  };
+
+  const escrowAddress = (contract: PublicKey, milestoneId: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), contract.toBuffer(), new anchor.BN(milestoneId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+  const vestingAddress = (contract: PublicKey, milestoneId: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vesting"), contract.toBuffer(), new anchor.BN(milestoneId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+  const vaultAddress = (escrow: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("vault"), escrow.toBuffer()], program.programId)[0];
+  const disputeAddress = (escrow: PublicKey, index = 0) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), escrow.toBuffer(), new anchor.BN(index).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    )[0];
+  const evidenceAddress = (dispute: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("evidence"), dispute.toBuffer()], program.programId)[0];
+  const arbiterAddress = (wallet: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("arbiter"), wallet.toBuffer()], program.programId)[0];
+  const registryPDA = PublicKey.findProgramAddressSync([Buffer.from("arbiter_registry")], program.programId)[0];
+
+  // Creates a contract owned by the provider wallet. By default every approving
+  // participant's weight is required and any single rejection vetoes it.
+  const createContract = async (
+    id: number,
+    participants: { wallet: PublicKey; role: object; weight: number }[],
+    opts: {
+      requiredWeight?: number;
+      vetoThreshold?: number;
+      arbiter?: PublicKey;
+      approvalDeadline?: anchor.BN;
+      orderedSigning?: boolean;
+    } = {}
+  ) => {
+    const contract = contractAddress(id);
+    await program.methods
+      .initializeContract(
+        new anchor.BN(id),
+        participants,
+        opts.requiredWeight ?? participants.reduce((sum, p) => sum + p.weight, 0),
+        opts.vetoThreshold ?? 1,
+        opts.arbiter ?? null,
+        opts.approvalDeadline ?? null,
+        opts.orderedSigning ?? false
+      )
+      .accounts({
+        contract,
+        creatorReputation: creatorRepPDA,
+        firstAmendment: amendmentAddress(contract, 2),
+        creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return contract;
+  };
+
+  // Funds a SOL milestone on `contract` from the provider wallet, paying `recipient`
+  const createEscrow = async (
+    contract: PublicKey,
+    id: number,
+    milestoneId: number,
+    recipient: PublicKey,
+    amount: number,
+    terms: { deadline?: anchor.BN; latePenalty?: object; earlyBonus?: object; retention?: object; splits?: object[] } = {}
+  ) => {
+    const escrow = escrowAddress(contract, milestoneId);
+    await program.methods
+      .initializeEscrowMilestone(
+        new anchor.BN(milestoneId),
+        new anchor.BN(id),
+        `Milestone ${milestoneId}`,
+        new anchor.BN(amount),
+        recipient,
+        terms.deadline ?? new anchor.BN(Math.floor(Date.now() / 1000) + 86_400),
+        terms.latePenalty ?? null,
+        terms.earlyBonus ?? null,
+        terms.retention ?? null,
+        terms.splits ?? []
+      )
+      .accounts({
+        escrowMilestone: escrow,
+        contract,
+        creatorReputation: creatorRepPDA,
+        creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return escrow;
+  };
+
+  // Creates a Token-2022 mint, with the provider wallet as mint authority, carrying one extension
+  const createMint2022 = async (extension: ExtensionType, initExtension: (mint: PublicKey) => TransactionInstruction) => {
+    const mint = Keypair.generate();
+    const space = getMintLen([extension]);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: creator,
+          newAccountPubkey: mint.publicKey,
+          space,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        initExtension(mint.publicKey),
+        createInitializeMintInstruction(mint.publicKey, 6, creator, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [mint]
+    );
+    return mint.publicKey;
+  };
+
+  // Funds a token milestone from `creatorTokens`, which the provider wallet owns
+  const createTokenEscrow = async (
+    contract: PublicKey,
+    id: number,
+    milestoneId: number,
+    recipient: PublicKey,
+    mint: PublicKey,
+    creatorTokens: PublicKey,
+    amount: number,
+    tokenProgram = TOKEN_PROGRAM_ID
+  ) => {
+    const escrow = escrowAddress(contract, milestoneId);
+    await program.methods
+      .initializeTokenEscrowMilestone(
+        new anchor.BN(milestoneId),
+        new anchor.BN(id),
+        `Milestone ${milestoneId}`,
+        new anchor.BN(amount),
+        recipient,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 86_400),
+        null,
+        null,
+        null,
+        []
+      )
+      .accounts({
+        escrowMilestone: escrow,
+        vault: vaultAddress(escrow),
+        mint,
+        creatorTokenAccount: creatorTokens,
+        contract,
+        creatorReputation: creatorRepPDA,
+        creator,
+        tokenProgram,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return escrow;
+  };
+
+  const createVesting = async (
+    contract: PublicKey,
+    id: number,
+    milestoneId: number,
+    recipient: PublicKey,
+    amount: number,
+    schedule: { startTs: anchor.BN; cliffTs: anchor.BN | null; endTs: anchor.BN }
+  ) => {
+    const vesting = vestingAddress(contract, milestoneId);
+    await program.methods
+      .initializeVestingMilestone(
+        new anchor.BN(milestoneId),
+        new anchor.BN(id),
+        `Vesting ${milestoneId}`,
+        new anchor.BN(amount),
+        recipient,
+        schedule
+      )
+      .accounts({
+        vestingMilestone: vesting,
+        contract,
+        creatorReputation: creatorRepPDA,
+        creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return vesting;
+  };
+
+  // Accounts that pay out a SOL milestone to its lead recipient; the token accounts stay empty
+  const solPayout = (escrow: PublicKey, contract: PublicKey, vendor: PublicKey) => ({
+    escrowMilestone: escrow,
+    contract,
+    recipient: vendor,
+    vault: null,
+    mint: null,
+    vendorReputation: reputationAddress(vendor),
+    clientReputation: creatorRepPDA,
+    creator,
+    creatorTokenAccount: null,
+    tokenProgram: null,
+  });
+
+  // The vendor marks the milestone delivered and every approver (the creator, the vendor and
+  // any `otherApprovers`) signs off
+  const completeMilestone = async (escrow: PublicKey, contract: PublicKey, vendor: Keypair, otherApprovers: Keypair[] = []) => {
+    await program.methods
+      .markMilestoneComplete()
+      .accounts({
+        escrowMilestone: escrow,
+        contract,
+        vendorReputation: reputationAddress(vendor.publicKey),
+        marker: vendor.publicKey,
+      })
+      .signers([vendor])
+      .rpc();
+    await program.methods
+      .approveMilestoneRelease()
+      .accounts({ escrowMilestone: escrow, contract, approverReputation: creatorRepPDA, approver: creator })
+      .rpc();
+    for (const approver of [vendor, ...otherApprovers]) {
+      await program.methods
+        .approveMilestoneRelease()
+        .accounts({
+          escrowMilestone: escrow,
+          contract,
+          approverReputation: reputationAddress(approver.publicKey),
+          approver: approver.publicKey,
+        })
+        .signers([approver])
+        .rpc();
+    }
+  };
+
   before("Create reputation accounts", async () => {
     // Create reputation account for creator
     [creatorRepPDA] = PublicKey.findProgramAddressSync(
@@ -43,42 +362,28 @@ describe("agreed_contracts", () => {
   });
 
   it("Initializes a contract", async () => {
-    const participants = [creator, participant1.publicKey, participant2.publicKey];
+    const participants = [
+      asVendor(creator),
+      asClient(participant1.publicKey),
+      asClient(participant2.publicKey),
+    ];
     
-    [contractPDA] = PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("contract"),
-        new anchor.BN(contractId).toArrayLike(Buffer, "le", 8),
-        creator.toBuffer(),
-      ],
-      program.programId
-    );
-
     // Get reputation before
     const repBefore = await program.account.userReputation.fetch(creatorRepPDA);
     const createdBefore = repBefore.contractsCreated;
 
-    await program.methods
-      .initializeContract(
-        new anchor.BN(contractId),
-        participants,
-        3 // All must approve
-      )
-      .accounts({
-        contract: contractPDA,
-        creatorReputation: creatorRepPDA,
-        creator: creator,
-        systemProgram: anchor.web3.SystemProgram.programId,
-      })
-      .rpc();
+    // All must approve, each with weight 1
+    contractPDA = await createContract(contractId, participants);
+
+    await setTermsDocument(contractPDA);
 
     const contract = await program.account.contract.fetch(contractPDA);
     
     assert.equal(contract.contractId.toNumber(), contractId);
     assert.equal(contract.creator.toBase58(), creator.toBase58());
     assert.equal(contract.participants.length, 3);
-    assert.equal(contract.requiredApprovals, 3);
-    assert.equal(contract.currentApprovals, 0);
+    assert.equal(contract.requiredWeight, 3);
+    assert.equal(contract.currentWeight, 0);
     assert.ok(contract.status.hasOwnProperty("active"));
 
     // Check reputation incremented
@@ -92,7 +397,7 @@ describe("agreed_contracts", () => {
     const approvedBefore = repBefore.contractsApproved;
 
     await program.methods
-      .approveContract()
+      .approveContract(termsHash)
       .accounts({
         contract: contractPDA,
         approverReputation: creatorRepPDA,
@@ -101,7 +406,7 @@ describe("agreed_contracts", () => {
       .rpc();
 
     const contract = await program.account.contract.fetch(contractPDA);
-    assert.equal(contract.currentApprovals, 1);
+    assert.equal(contract.currentWeight, 1);
     assert.ok(contract.status.hasOwnProperty("active")); // Still active
 
     // Check reputation incremented
@@ -137,7 +442,7 @@ describe("agreed_contracts", () => {
         .rpc();
 
       await program.methods
-        .approveContract()
+        .approveContract(termsHash)
         .accounts({
           contract: contractPDA,
           approverReputation: p1RepPDA,
@@ -147,7 +452,7 @@ describe("agreed_contracts", () => {
         .rpc();
 
       const contract = await program.account.contract.fetch(contractPDA);
-      assert.equal(contract.currentApprovals, 2);
+      assert.equal(contract.currentWeight, 2);
       assert.ok(contract.status.hasOwnProperty("active")); // Still active
     } catch (err) {
       if (err.toString().includes("429")) {
@@ -186,7 +491,7 @@ describe("agreed_contracts", () => {
         .rpc();
 
       await program.methods
-        .approveContract()
+        .approveContract(termsHash)
         .accounts({
           contract: contractPDA,
           approverReputation: p2RepPDA,
@@ -196,7 +501,7 @@ describe("agreed_contracts", () => {
         .rpc();
 
       const contract = await program.account.contract.fetch(contractPDA);
-      assert.equal(contract.currentApprovals, 3);
+      assert.equal(contract.currentWeight, 3);
       assert.ok(contract.status.hasOwnProperty("completed")); // Now completed!
     } catch (err) {
       if (err.toString().includes("429")) {
@@ -213,29 +518,9 @@ describe("agreed_contracts", () => {
       const nonParticipant = Keypair.generate();
       
       // Create new contract for this test
-      const newContractId = contractId + 1;
-      const [newContractPDA] = PublicKey.findProgramAddressSync(
-        [
-          Buffer.from("contract"),
-          new anchor.BN(newContractId).toArrayLike(Buffer, "le", 8),
-          creator.toBuffer(),
-        ],
-        program.programId
-      );
+      const newContractPDA = await createContract(contractId + 1, [asVendor(creator)]);
 
-      await program.methods
-        .initializeContract(
-          new anchor.BN(newContractId),
-          [creator],
-          1
-        )
-        .accounts({
-          contract: newContractPDA,
-          creatorReputation: creatorRepPDA,
-          creator: creator,
-          systemProgram: anchor.web3.SystemProgram.programId,
-        })
-        .rpc();
+      await setTermsDocument(newContractPDA);
 
       // Airdrop to non-participant FIRST
       await provider.connection.requestAirdrop(
@@ -263,7 +548,7 @@ describe("agreed_contracts", () => {
 
       try {
         await program.methods
-          .approveContract()
+          .approveContract(termsHash)
           .accounts({
             contract: newContractPDA,
             approverReputation: npRepPDA,
@@ -287,33 +572,97 @@ describe("agreed_contracts", () => {
   });
 
   it("Fails when participant tries to approve twice", async () => {
-    const newContractId = contractId + 2;
-    const [newContractPDA] = PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("contract"),
-        new anchor.BN(newContractId).toArrayLike(Buffer, "le", 8),
-        creator.toBuffer(),
-      ],
-      program.programId
-    );
+    const newContractPDA = await createContract(contractId + 2, [asVendor(creator), asClient(participant1.publicKey)]);
 
+    await setTermsDocument(newContractPDA);
+
+    // First approval
     await program.methods
-      .initializeContract(
-        new anchor.BN(newContractId),
-        [creator, participant1.publicKey],
-        2
-      )
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: creatorRepPDA,
+        approver: creator,
+      })
+      .rpc();
+
+    // Try to approve again
+    try {
+      await program.methods
+        .approveContract(termsHash)
+        .accounts({
+          contract: newContractPDA,
+          approverReputation: creatorRepPDA,
+          approver: creator,
+        })
+        .rpc();
+      
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "AlreadyApproved");
+    }
+  });
+
+  it("Creator can cancel contract", async () => {
+    const newContractPDA = await createContract(contractId + 3, [asVendor(creator)]);
+
+    await program.methods
+      .cancelContract()
       .accounts({
         contract: newContractPDA,
-        creatorReputation: creatorRepPDA,
         creator: creator,
-        systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
 
-    // First approval
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.ok(contract.status.hasOwnProperty("cancelled"));
+  });
+
+  it("Fails when the arbiter is also a participant", async () => {
+    try {
+      await createContract(contractId + 4, [asVendor(creator), asClient(participant1.publicKey)], {
+        arbiter: participant1.publicKey,
+      });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ArbiterIsParticipant");
+    }
+  });
+
+  it("Does not count observers toward the approval threshold", async () => {
+    try {
+      // Only the creator's weight counts, so a threshold of 2 can never be met
+      await createContract(contractId + 7, [asVendor(creator), asObserver(participant1.publicKey)], { requiredWeight: 2 });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidApprovalThreshold");
+    }
+  });
+
+  it("Fails when no approval weight is required", async () => {
+    try {
+      await createContract(contractId + 15, [asVendor(creator)], { requiredWeight: 0 });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidApprovalThreshold");
+    }
+  });
+
+  it("Completes once the approvers' combined weight meets the threshold", async () => {
+    const newContractPDA = await createContract(
+      contractId + 8,
+      [asClient(creator, 3), asVendor(participant1.publicKey), asVendor(participant2.publicKey)],
+      { requiredWeight: 3 }
+    );
+
+    await setTermsDocument(newContractPDA);
+
+    // The creator's weight alone outweighs both vendors
     await program.methods
-      .approveContract()
+      .approveContract(termsHash)
       .accounts({
         contract: newContractPDA,
         approverReputation: creatorRepPDA,
@@ -321,58 +670,2063 @@ describe("agreed_contracts", () => {
       })
       .rpc();
 
-    // Try to approve again
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.currentWeight, 3);
+    assert.ok(contract.status.hasOwnProperty("completed"));
+  });
+
+  it("Rejects a contract once the veto threshold is reached", async () => {
+    const newContractPDA = await createContract(contractId + 9, [asVendor(creator), asClient(participant1.publicKey)]);
+
+    await setTermsDocument(newContractPDA);
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: creatorRepPDA,
+        approver: creator,
+      })
+      .rpc();
+
+    // An approver has to take their approval back before voting no
+    await program.methods
+      .revokeApproval()
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: creatorRepPDA,
+        approver: creator,
+      })
+      .rpc();
+
+    await program.methods
+      .rejectContract()
+      .accounts({
+        contract: newContractPDA,
+        rejector: creator,
+      })
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.currentWeight, 0);
+    assert.equal(contract.rejectors.length, 1);
+    assert.ok(contract.status.hasOwnProperty("rejected"));
+  });
+
+  it("Fails when the approval deadline is already in the past", async () => {
+    try {
+      await createContract(contractId + 10, [asVendor(creator)], {
+        approvalDeadline: new anchor.BN(Math.floor(Date.now() / 1000) - 60),
+      });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidDeadline");
+    }
+  });
+
+  it("Fails when a participant signs out of turn", async () => {
+    // participant1 has to sign before the creator
+    const newContractPDA = await createContract(contractId + 11, [asClient(participant1.publicKey), asVendor(creator)], {
+      orderedSigning: true,
+    });
+
+    await setTermsDocument(newContractPDA);
+
     try {
       await program.methods
-        .approveContract()
+        .approveContract(termsHash)
         .accounts({
           contract: newContractPDA,
           approverReputation: creatorRepPDA,
           approver: creator,
         })
         .rpc();
-      
+
       assert.fail("Should have thrown error");
     } catch (err) {
-      assert.include(err.toString(), "AlreadyApproved");
+      assert.include(err.toString(), "SignedOutOfTurn");
     }
   });
 
-  it("Creator can cancel contract", async () => {
-    const newContractId = contractId + 3;
-    const [newContractPDA] = PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("contract"),
-        new anchor.BN(newContractId).toArrayLike(Buffer, "le", 8),
-        creator.toBuffer(),
-      ],
+  it("Versions the contract document through amendments", async () => {
+    const newContractPDA = await createContract(contractId + 5, [asVendor(creator)]);
+
+    await program.methods
+      .setContractDocument(sha256Document("initial terms"))
+      .accounts({
+        contract: newContractPDA,
+        updater: creator,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .setContractDocument(sha256Document("overwritten terms"))
+        .accounts({
+          contract: newContractPDA,
+          updater: creator,
+        })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "DocumentAlreadySet");
+    }
+
+    const version = Buffer.alloc(4);
+    version.writeUInt32LE(2);
+    const [amendmentPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("amendment"), newContractPDA.toBuffer(), version],
       program.programId
     );
 
+    // A single required approval means the proposer's own approval applies it
     await program.methods
-      .initializeContract(
-        new anchor.BN(newContractId),
-        [creator],
-        1
-      )
+      .proposeContractAmendment(sha256Document("amended terms"))
       .accounts({
+        amendment: amendmentPDA,
         contract: newContractPDA,
-        creatorReputation: creatorRepPDA,
-        creator: creator,
+        proposer: creator,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
 
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.version, 2);
+    assert.deepEqual(contract.document, sha256Document("amended terms"));
+
+    const amendment = await program.account.contractAmendment.fetch(amendmentPDA);
+    assert.deepEqual(amendment.previousDocument, sha256Document("initial terms"));
+    assert.ok(amendment.status.hasOwnProperty("applied"));
+  });
+
+  it("Resets approvals when the document is amended", async () => {
+    const newContractPDA = await createContract(contractId + 12, [asVendor(creator)]);
+
+    await setTermsDocument(newContractPDA);
+    const approvedBefore = (await program.account.userReputation.fetch(creatorRepPDA)).contractsApproved;
+
     await program.methods
-      .cancelContract()
+      .approveContract(termsHash)
       .accounts({
         contract: newContractPDA,
-        creator: creator,
+        approverReputation: creatorRepPDA,
+        approver: creator,
       })
       .rpc();
 
-    const contract = await program.account.contract.fetch(newContractPDA);
-    assert.ok(contract.status.hasOwnProperty("cancelled"));
-  });
-});
+    const version = Buffer.alloc(4);
+    version.writeUInt32LE(2);
+    const [amendmentPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("amendment"), newContractPDA.toBuffer(), version],
+      program.programId
+    );
 
+    // Applying it takes back the creator's approval, so their reputation comes along
+    await program.methods
+      .proposeContractAmendment(sha256Document("revised terms"))
+      .accounts({
+        amendment: amendmentPDA,
+        contract: newContractPDA,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([{ pubkey: creatorRepPDA, isSigner: false, isWritable: true }])
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.approvers.length, 0);
+    assert.equal(contract.currentWeight, 0);
+    assert.ok(contract.status.hasOwnProperty("active"));
+    assert.equal((await program.account.userReputation.fetch(creatorRepPDA)).contractsApproved, approvedBefore);
+
+    // The old approval no longer describes the contract's text
+    try {
+      await program.methods
+        .approveContract(termsHash)
+        .accounts({
+          contract: newContractPDA,
+          approverReputation: creatorRepPDA,
+          approver: creator,
+        })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "DocumentHashMismatch");
+    }
+  });
+
+  it("Adds a participant once the approval threshold is met", async () => {
+    const newContractPDA = await createContract(contractId + 6, [asVendor(creator)]);
+
+    const [changePDA] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("participant_change"),
+        newContractPDA.toBuffer(),
+        participant1.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+
+    // The creator's own approval meets the threshold, so the change applies at once
+    await program.methods
+      .proposeParticipantChange(participant1.publicKey, { add: { role: { client: {} }, weight: 1 } })
+      .accounts({
+        participantChange: changePDA,
+        contract: newContractPDA,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.participants.length, 2);
+    assert.ok(contract.participants[1].wallet.equals(participant1.publicKey));
+    assert.ok(contract.participants[1].role.hasOwnProperty("client"));
+    assert.isNull(await provider.connection.getAccountInfo(changePDA));
+  });
+
+  it("Approves a contract whose document is referenced by CID", async () => {
+    const newContractPDA = await createContract(contractId + 13, [asVendor(creator)]);
+
+    await program.methods
+      .setContractDocument({ cidV0: { 0: Array.from(termsMultihash) } })
+      .accounts({ contract: newContractPDA, updater: creator })
+      .rpc();
+
+    await program.methods
+      .approveContract(documentFingerprint(0, termsMultihash))
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: creatorRepPDA,
+        approver: creator,
+      })
+      .rpc();
+
+    const version = Buffer.alloc(4);
+    version.writeUInt32LE(2);
+    const [amendmentPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("amendment"), newContractPDA.toBuffer(), version],
+      program.programId
+    );
+
+    // The same content, now addressed by its CIDv1
+    await program.methods
+      .proposeContractAmendment({ cidV1: { 0: termsCidV1 } })
+      .accounts({
+        amendment: amendmentPDA,
+        contract: newContractPDA,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([{ pubkey: creatorRepPDA, isSigner: false, isWritable: true }])
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.deepEqual(Buffer.from(contract.document.cidV1[0]), termsCidV1);
+    assert.ok(contract.status.hasOwnProperty("active"));
+  });
+
+  it("Fails when a CIDv0 is not a sha2-256 multihash", async () => {
+    const newContractPDA = await createContract(contractId + 14, [asVendor(creator)]);
+
+    // Swap the 0x12 (sha2-256) multihash code for 0x13 (sha2-512)
+    const multihash = Array.from(termsMultihash);
+    multihash[0] = 0x13;
+
+    try {
+      await program.methods
+        .setContractDocument({ cidV0: { 0: multihash } })
+        .accounts({ contract: newContractPDA, updater: creator })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidDocumentRef");
+    }
+  });
+
+  it("Expires a contract after its deadline and reverses the approvers' deals", async () => {
+    const approver = await fundedWallet();
+    const approverRepPDA = reputationAddress(approver.publicKey);
+    const deadline = (await chainTime()) + 5;
+
+    const newContractPDA = await createContract(
+      contractId + 16,
+      [asVendor(creator), asClient(approver.publicKey)],
+      { approvalDeadline: new anchor.BN(deadline) }
+    );
+    await setTermsDocument(newContractPDA);
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: approverRepPDA,
+        approver: approver.publicKey,
+      })
+      .signers([approver])
+      .rpc();
+    assert.equal((await program.account.userReputation.fetch(approverRepPDA)).dealsAsClient, 1);
+
+    try {
+      await program.methods
+        .expireContract()
+        .accounts({ contract: newContractPDA, creatorReputation: creatorRepPDA })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ApprovalDeadlineNotPassed");
+    }
+
+    await waitForChainTime(deadline);
+
+    // Every approver other than the creator needs their reputation passed along
+    try {
+      await program.methods
+        .expireContract()
+        .accounts({ contract: newContractPDA, creatorReputation: creatorRepPDA })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidApproverReputation");
+    }
+
+    const creatorDealsBefore = (await program.account.userReputation.fetch(creatorRepPDA)).dealsAsVendor;
+
+    await program.methods
+      .expireContract()
+      .accounts({ contract: newContractPDA, creatorReputation: creatorRepPDA })
+      .remainingAccounts([{ pubkey: approverRepPDA, isSigner: false, isWritable: true }])
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.ok(contract.status.hasOwnProperty("expired"));
+    assert.equal((await program.account.userReputation.fetch(approverRepPDA)).dealsAsClient, 0);
+    assert.equal(
+      (await program.account.userReputation.fetch(creatorRepPDA)).dealsAsVendor,
+      creatorDealsBefore - 1
+    );
+  });
+
+  it("Closes a cancelled contract and returns its rent to the creator", async () => {
+    const newContractPDA = await createContract(contractId + 17, [asVendor(creator)]);
+
+    try {
+      await program.methods
+        .closeContract()
+        .accounts({ contract: newContractPDA, creator })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ContractStillOpen");
+    }
+
+    await program.methods
+      .cancelContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+
+    const rent = (await provider.connection.getAccountInfo(newContractPDA)).lamports;
+    const balanceBefore = await provider.connection.getBalance(creator);
+
+    await program.methods
+      .closeContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(newContractPDA));
+    // Less the transaction fee
+    assert.isAbove(await provider.connection.getBalance(creator), balanceBefore + rent - 10_000);
+  });
+
+  it("Fails to reuse the id of a closed contract that was amended", async () => {
+    const id = contractId + 18;
+    const newContractPDA = await createContract(id, [asVendor(creator)]);
+    await setTermsDocument(newContractPDA);
+
+    await program.methods
+      .proposeContractAmendment(sha256Document("amended terms"))
+      .accounts({
+        amendment: amendmentAddress(newContractPDA, 2),
+        contract: newContractPDA,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .cancelContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+    await program.methods
+      .closeContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+
+    // The applied amendment would otherwise sit in the new contract's history
+    try {
+      await createContract(id, [asVendor(creator)]);
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ContractIdReused");
+    }
+  });
+
+  it("Completes an ordered contract signed in turn", async () => {
+    const first = await fundedWallet();
+    const newContractPDA = await createContract(contractId + 19, [asClient(first.publicKey), asVendor(creator)], {
+      orderedSigning: true,
+    });
+    await setTermsDocument(newContractPDA);
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: reputationAddress(first.publicKey),
+        approver: first.publicKey,
+      })
+      .signers([first])
+      .rpc();
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: creatorRepPDA,
+        approver: creator,
+      })
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.deepEqual(contract.approvers, [first.publicKey, creator]);
+    assert.ok(contract.status.hasOwnProperty("completed"));
+  });
+
+  it("Passes the turn on when an ordered signer rejects", async () => {
+    const first = await fundedWallet();
+    const last = await fundedWallet();
+    // One rejection doesn't veto, and the other two can still carry it
+    const newContractPDA = await createContract(
+      contractId + 20,
+      [asClient(first.publicKey), asVendor(creator), asClient(last.publicKey)],
+      { requiredWeight: 2, vetoThreshold: 2, orderedSigning: true }
+    );
+    await setTermsDocument(newContractPDA);
+
+    await program.methods
+      .rejectContract()
+      .accounts({ contract: newContractPDA, rejector: first.publicKey })
+      .signers([first])
+      .rpc();
+
+    try {
+      await program.methods
+        .approveContract(termsHash)
+        .accounts({
+          contract: newContractPDA,
+          approverReputation: reputationAddress(last.publicKey),
+          approver: last.publicKey,
+        })
+        .signers([last])
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "SignedOutOfTurn");
+    }
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: creatorRepPDA,
+        approver: creator,
+      })
+      .rpc();
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: reputationAddress(last.publicKey),
+        approver: last.publicKey,
+      })
+      .signers([last])
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.rejectors.length, 1);
+    assert.equal(contract.currentWeight, 2);
+    assert.ok(contract.status.hasOwnProperty("completed"));
+  });
+
+  it("Reverses the approvers' recorded deals when an amendment is applied", async () => {
+    const approver = await fundedWallet();
+    const approverRepPDA = reputationAddress(approver.publicKey);
+    const newContractPDA = await createContract(contractId + 21, [asVendor(creator), asClient(approver.publicKey)], {
+      requiredWeight: 1,
+    });
+    await setTermsDocument(newContractPDA);
+
+    await program.methods
+      .approveContract(termsHash)
+      .accounts({
+        contract: newContractPDA,
+        approverReputation: approverRepPDA,
+        approver: approver.publicKey,
+      })
+      .signers([approver])
+      .rpc();
+
+    const amendmentPDA = amendmentAddress(newContractPDA, 2);
+    const propose = () =>
+      program.methods
+        .proposeContractAmendment(sha256Document("amended terms"))
+        .accounts({
+          amendment: amendmentPDA,
+          contract: newContractPDA,
+          proposer: creator,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        });
+
+    try {
+      await propose().rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidApproverReputation");
+    }
+
+    await propose()
+      .remainingAccounts([{ pubkey: approverRepPDA, isSigner: false, isWritable: true }])
+      .rpc();
+
+    const contract = await program.account.contract.fetch(newContractPDA);
+    assert.equal(contract.version, 2);
+    assert.equal(contract.approvers.length, 0);
+
+    const reputation = await program.account.userReputation.fetch(approverRepPDA);
+    assert.equal(reputation.contractsApproved, 0);
+    assert.equal(reputation.dealsAsClient, 0);
+  });
+
+  it("Pays an SPL token milestone out of its vault", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 23;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+
+    const mint = await createMint(provider.connection, payer, creator, null, 6);
+    const creatorTokens = await createAccount(provider.connection, payer, mint, creator);
+    const vendorTokens = await createAccount(provider.connection, payer, mint, vendor.publicKey);
+    await mintTo(provider.connection, payer, mint, creatorTokens, payer, 1_000_000);
+
+    const escrow = await createTokenEscrow(contract, id, 1, vendor.publicKey, mint, creatorTokens, 1_000_000);
+    assert.equal(Number((await getAccount(provider.connection, vaultAddress(escrow))).amount), 1_000_000);
+    await completeMilestone(escrow, contract, vendor);
+
+    const payout = {
+      escrowMilestone: escrow,
+      contract,
+      recipient: vendorTokens,
+      vault: vaultAddress(escrow),
+      mint,
+      vendorReputation: reputationAddress(vendor.publicKey),
+      clientReputation: creatorRepPDA,
+      creator,
+      creatorTokenAccount: creatorTokens,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    try {
+      await program.methods
+        .releaseEscrowFunds()
+        .accounts({ ...payout, vault: null, mint: null, tokenProgram: null })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "MissingTokenAccounts");
+    }
+
+    // The payout has to land in a token account the recipient owns
+    try {
+      await program.methods
+        .releaseEscrowFunds()
+        .accounts({ ...payout, recipient: creatorTokens })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidDestination");
+    }
+
+    await program.methods.releaseEscrowFunds().accounts(payout).rpc();
+
+    assert.equal(Number((await getAccount(provider.connection, vendorTokens)).amount), 1_000_000);
+    const milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.ok(milestone.status.hasOwnProperty("released"));
+    assert.ok(milestone.mint.equals(mint));
+    assert.equal(milestone.amountDelivered.toNumber(), 1_000_000);
+  });
+
+  it("Records what a Token-2022 transfer-fee vault actually received", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 24;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+
+    // 1% of every transfer is withheld
+    const mint = await createMint2022(ExtensionType.TransferFeeConfig, (mint) =>
+      createInitializeTransferFeeConfigInstruction(mint, creator, creator, 100, BigInt(1_000_000_000), TOKEN_2022_PROGRAM_ID)
+    );
+    const creatorTokens = await createAccount(provider.connection, payer, mint, creator, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+    const vendorTokens = await createAccount(
+      provider.connection,
+      payer,
+      mint,
+      vendor.publicKey,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(provider.connection, payer, mint, creatorTokens, payer, 1_000_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    const escrow = await createTokenEscrow(
+      contract,
+      id,
+      1,
+      vendor.publicKey,
+      mint,
+      creatorTokens,
+      1_000_000,
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal((await program.account.escrowMilestone.fetch(escrow)).amount.toNumber(), 990_000);
+
+    await completeMilestone(escrow, contract, vendor);
+    await program.methods
+      .releaseEscrowFunds()
+      .accounts({
+        escrowMilestone: escrow,
+        contract,
+        recipient: vendorTokens,
+        vault: vaultAddress(escrow),
+        mint,
+        vendorReputation: reputationAddress(vendor.publicKey),
+        clientReputation: creatorRepPDA,
+        creator,
+        creatorTokenAccount: creatorTokens,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+
+    // The payout is charged the fee again on its way out
+    const received = Number(
+      (await getAccount(provider.connection, vendorTokens, undefined, TOKEN_2022_PROGRAM_ID)).amount
+    );
+    assert.equal(received, 980_100);
+    assert.equal((await program.account.escrowMilestone.fetch(escrow)).amountDelivered.toNumber(), received);
+  });
+
+  it("Fails to escrow a mint with an unsupported extension", async () => {
+    const vendor = Keypair.generate();
+    const id = contractId + 25;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+
+    const mint = await createMint2022(ExtensionType.NonTransferable, (mint) =>
+      createInitializeNonTransferableMintInstruction(mint, TOKEN_2022_PROGRAM_ID)
+    );
+    const creatorTokens = await createAccount(provider.connection, payer, mint, creator, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+    await mintTo(provider.connection, payer, mint, creatorTokens, payer, 1_000_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    try {
+      await createTokenEscrow(contract, id, 1, vendor.publicKey, mint, creatorTokens, 1_000_000, TOKEN_2022_PROGRAM_ID);
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "UnsupportedMintExtension");
+    }
+  });
+
+  it("Releases a milestone in tranches and keeps a running ledger", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 26;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000);
+    const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+
+    await completeMilestone(escrow, contract, vendor);
+
+    // Only the creator decides how much of an approved milestone to pay
+    try {
+      await program.methods
+        .releasePartialEscrowFunds(new anchor.BN(300_000))
+        .accounts({ ...solPayout(escrow, contract, vendor.publicKey), creator: vendor.publicKey })
+        .signers([vendor])
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "OnlyCreatorCanReleasePartial");
+    }
+
+    await program.methods
+      .releasePartialEscrowFunds(new anchor.BN(300_000))
+      .accounts(solPayout(escrow, contract, vendor.publicKey))
+      .rpc();
+
+    let milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.equal(milestone.releasedAmount.toNumber(), 300_000);
+    assert.ok(milestone.status.hasOwnProperty("funded"));
+    assert.equal(milestone.approvals.length, 0);
+
+    // The next tranche needs a fresh completion mark and approvals
+    await completeMilestone(escrow, contract, vendor);
+    try {
+      await program.methods
+        .releasePartialEscrowFunds(new anchor.BN(800_000))
+        .accounts(solPayout(escrow, contract, vendor.publicKey))
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidAmount");
+    }
+
+    await program.methods
+      .releasePartialEscrowFunds(new anchor.BN(700_000))
+      .accounts(solPayout(escrow, contract, vendor.publicKey))
+      .rpc();
+
+    milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.equal(milestone.releasedAmount.toNumber(), 1_000_000);
+    assert.ok(milestone.status.hasOwnProperty("released"));
+    // The provider wallet paid the vendor's transaction fees
+    assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 1_000_000);
+  });
+
+  it("Cancels a partly released milestone and then closes its escrow", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 27;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000);
+
+    await completeMilestone(escrow, contract, vendor);
+    await program.methods
+      .releasePartialEscrowFunds(new anchor.BN(250_000))
+      .accounts(solPayout(escrow, contract, vendor.publicKey))
+      .rpc();
+
+    const creatorBefore = await provider.connection.getBalance(creator);
+
+    await program.methods
+      .cancelEscrowMilestone()
+      .accounts({
+        escrowMilestone: escrow,
+        contract,
+        creatorReputation: creatorRepPDA,
+        creator,
+        vault: null,
+        mint: null,
+        creatorTokenAccount: null,
+        tokenProgram: null,
+      })
+      .rpc();
+
+    // The unreleased 750,000 comes back, less the transaction fee
+    assert.approximately((await provider.connection.getBalance(creator)) - creatorBefore, 750_000, 10_000);
+    const milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.ok(milestone.status.hasOwnProperty("cancelled"));
+    assert.isNotNull(milestone.settledAt);
+
+    // Then the escrow's rent
+    const rent = await provider.connection.getBalance(escrow);
+    const creatorBeforeClose = await provider.connection.getBalance(creator);
+    await program.methods
+      .closeEscrowMilestone()
+      .accounts({ escrowMilestone: escrow, creator, vault: null, tokenProgram: null })
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(escrow));
+    assert.approximately((await provider.connection.getBalance(creator)) - creatorBeforeClose, rent, 10_000);
+  });
+
+  it("Streams vested funds and refunds the unvested rest on cancellation", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 28;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const now = await chainTime();
+    const vesting = await createVesting(contract, id, 1, vendor.publicKey, 1_000_000, {
+      startTs: new anchor.BN(now - 100),
+      cliffTs: null,
+      endTs: new anchor.BN(now + 100),
+    });
+
+    const claim = (recipient: Keypair) =>
+      program.methods
+        .claimVestedFunds()
+        .accounts({
+          vestingMilestone: vesting,
+          vendorReputation: reputationAddress(recipient.publicKey),
+          recipient: recipient.publicKey,
+          vault: null,
+          mint: null,
+          recipientTokenAccount: null,
+          tokenProgram: null,
+        })
+        .signers([recipient])
+        .rpc();
+
+    const outsider = await fundedWallet();
+    try {
+      await claim(outsider);
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "OnlyRecipientCanClaim");
+    }
+
+    // Roughly half the schedule has elapsed
+    await claim(vendor);
+    let milestone = await program.account.vestingMilestone.fetch(vesting);
+    const claimed = milestone.claimedAmount.toNumber();
+    assert.isAbove(claimed, 400_000);
+    assert.isBelow(claimed, 1_000_000);
+
+    await program.methods
+      .cancelVestingMilestone()
+      .accounts({
+        vestingMilestone: vesting,
+        creatorReputation: creatorRepPDA,
+        creator,
+        vault: null,
+        mint: null,
+        creatorTokenAccount: null,
+        tokenProgram: null,
+      })
+      .rpc();
+
+    milestone = await program.account.vestingMilestone.fetch(vesting);
+    assert.ok(milestone.status.hasOwnProperty("cancelled"));
+    assert.isBelow(milestone.amount.toNumber(), 1_000_000);
+
+    // Whatever vested before the cancellation can still be claimed
+    const vested = milestone.amount.toNumber();
+    if (vested > claimed) {
+      await claim(vendor);
+    }
+    milestone = await program.account.vestingMilestone.fetch(vesting);
+    assert.equal(milestone.claimedAmount.toNumber(), vested);
+    assert.ok(milestone.status.hasOwnProperty("cancelled"));
+  });
+
+  it("Fails to claim before the vesting cliff", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 29;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const now = await chainTime();
+    const vesting = await createVesting(contract, id, 1, vendor.publicKey, 1_000_000, {
+      startTs: new anchor.BN(now - 100),
+      cliffTs: new anchor.BN(now + 3_600),
+      endTs: new anchor.BN(now + 7_200),
+    });
+
+    try {
+      await program.methods
+        .claimVestedFunds()
+        .accounts({
+          vestingMilestone: vesting,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          recipient: vendor.publicKey,
+          vault: null,
+          mint: null,
+          recipientTokenAccount: null,
+          tokenProgram: null,
+        })
+        .signers([vendor])
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "NothingToClaim");
+    }
+  });
+
+  it("Refunds and expires a milestone that missed its deadline", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 30;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const deadline = (await chainTime()) + 3;
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      deadline: new anchor.BN(deadline),
+    });
+
+    // Permissionless, so an outsider can crank it
+    const cranker = await fundedWallet();
+    const expire = () =>
+      program.methods
+        .expireMilestone()
+        .accounts({
+          escrowMilestone: escrow,
+          contract,
+          creator,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          vault: null,
+          mint: null,
+          creatorTokenAccount: null,
+          tokenProgram: null,
+          cranker: cranker.publicKey,
+        })
+        .signers([cranker])
+        .rpc();
+
+    try {
+      await expire();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "DeadlineNotReached");
+    }
+
+    await waitForChainTime(deadline);
+    const creatorBefore = await provider.connection.getBalance(creator);
+    await expire();
+
+    // The provider wallet pays the cranker's fee
+    assert.approximately((await provider.connection.getBalance(creator)) - creatorBefore, 1_000_000, 10_000);
+    assert.ok((await program.account.escrowMilestone.fetch(escrow)).status.hasOwnProperty("expired"));
+    assert.equal((await program.account.userReputation.fetch(reputationAddress(vendor.publicKey))).missedDeadlinesVendor, 1);
+  });
+
+  it("Fails to expire a milestone that already paid out a tranche", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 31;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const deadline = (await chainTime()) + 3;
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      deadline: new anchor.BN(deadline),
+    });
+
+    await completeMilestone(escrow, contract, vendor);
+    await program.methods
+      .releasePartialEscrowFunds(new anchor.BN(100_000))
+      .accounts(solPayout(escrow, contract, vendor.publicKey))
+      .rpc();
+    await waitForChainTime(deadline);
+
+    try {
+      await program.methods
+        .expireMilestone()
+        .accounts({
+          escrowMilestone: escrow,
+          contract,
+          creator,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          vault: null,
+          mint: null,
+          creatorTokenAccount: null,
+          tokenProgram: null,
+          cranker: creator,
+        })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "MilestonePartiallyReleased");
+    }
+  });
+
+  it("Sends the late-delivery penalty back to the creator", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 32;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    // Just under two days overdue: two started days at 5% each
+    const deadline = (await chainTime()) - 2 * 86_400 + 600;
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      deadline: new anchor.BN(deadline),
+      latePenalty: { gracePeriodSeconds: new anchor.BN(0), bpsPerDay: 500, maxBps: 2_000 },
+    });
+
+    await completeMilestone(escrow, contract, vendor);
+    const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+    await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+
+    const milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.equal(milestone.penaltyAmount.toNumber(), 100_000);
+    assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 900_000);
+  });
+
+  it("Fails when the penalty cap exceeds the whole payout", async () => {
+    const vendor = Keypair.generate();
+    const id = contractId + 33;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+
+    try {
+      await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+        latePenalty: { gracePeriodSeconds: new anchor.BN(0), bpsPerDay: 500, maxBps: 10_001 },
+      });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidPenaltySchedule");
+    }
+  });
+
+  it("Pays the early-delivery bonus for delivery before the early-bird time", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 34;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      earlyBonus: { amount: new anchor.BN(200_000), earlyBirdTs: new anchor.BN((await chainTime()) + 3_600) },
+    });
+
+    await completeMilestone(escrow, contract, vendor);
+    const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+    await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+
+    assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 1_200_000);
+  });
+
+  it("Returns a missed early-delivery bonus to the creator", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 35;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      earlyBonus: { amount: new anchor.BN(200_000), earlyBirdTs: new anchor.BN((await chainTime()) - 60) },
+    });
+
+    await completeMilestone(escrow, contract, vendor);
+    const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+    const escrowBefore = await provider.connection.getBalance(escrow);
+    await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+
+    // The bonus leaves the escrow too, but towards the creator
+    assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 1_000_000);
+    assert.equal(escrowBefore - (await provider.connection.getBalance(escrow)), 1_200_000);
+  });
+
+  it("Holds back retention until the warranty period ends", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 36;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      retention: { bps: 1_000, warrantySeconds: new anchor.BN(3) },
+    });
+
+    await completeMilestone(escrow, contract, vendor);
+    const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+    await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+    assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 900_000);
+
+    const releaseRetention = () =>
+      program.methods
+        .releaseRetention()
+        .accounts({
+          escrowMilestone: escrow,
+          recipient: vendor.publicKey,
+          vault: null,
+          mint: null,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          tokenProgram: null,
+        })
+        .rpc();
+
+    try {
+      await releaseRetention();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "WarrantyPeriodActive");
+    }
+
+    const milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.equal(milestone.retainedAmount.toNumber(), 100_000);
+    await waitForChainTime(milestone.settledAt.toNumber() + 3);
+    await releaseRetention();
+
+    assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 1_000_000);
+    assert.equal((await program.account.escrowMilestone.fetch(escrow)).retainedAmount.toNumber(), 0);
+  });
+
+  it("Splits a milestone payout between its recipients", async () => {
+    const lead = await fundedWallet();
+    const partner = await fundedWallet();
+    const id = contractId + 37;
+    const contract = await createContract(id, [asClient(creator), asVendor(lead.publicKey), asVendor(partner.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, lead.publicKey, 1_000_000, {
+      splits: [
+        { wallet: lead.publicKey, shareBps: 6_000 },
+        { wallet: partner.publicKey, shareBps: 4_000 },
+      ],
+    });
+
+    await completeMilestone(escrow, contract, lead, [partner]);
+    const leadBefore = await provider.connection.getBalance(lead.publicKey);
+    const partnerBefore = await provider.connection.getBalance(partner.publicKey);
+
+    // Every co-recipient needs its wallet and reputation passed along
+    try {
+      await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, lead.publicKey)).rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "MissingRecipientAccounts");
+    }
+
+    await program.methods
+      .releaseEscrowFunds()
+      .accounts(solPayout(escrow, contract, lead.publicKey))
+      .remainingAccounts([
+        { pubkey: partner.publicKey, isSigner: false, isWritable: true },
+        { pubkey: reputationAddress(partner.publicKey), isSigner: false, isWritable: true },
+      ])
+      .rpc();
+
+    assert.equal((await provider.connection.getBalance(lead.publicKey)) - leadBefore, 600_000);
+    assert.equal((await provider.connection.getBalance(partner.publicKey)) - partnerBefore, 400_000);
+    const partnerRep = await program.account.userReputation.fetch(reputationAddress(partner.publicKey));
+    assert.equal(partnerRep.completedAsVendor, 1);
+  });
+
+  it("Fails when split shares don't add up to the whole payout", async () => {
+    const lead = Keypair.generate();
+    const partner = Keypair.generate();
+    const id = contractId + 38;
+    const contract = await createContract(id, [asClient(creator), asVendor(lead.publicKey), asVendor(partner.publicKey)]);
+
+    try {
+      await createEscrow(contract, id, 1, lead.publicKey, 1_000_000, {
+        splits: [
+          { wallet: lead.publicKey, shareBps: 6_000 },
+          { wallet: partner.publicKey, shareBps: 3_000 },
+        ],
+      });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidSplits");
+    }
+  });
+
+  it("Tops up a funded milestone once every approver consents", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 39;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000);
+    const [amendment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("milestone_amendment"), escrow.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .proposeMilestoneAmendment(new anchor.BN(1_500_000), null, "Extended scope")
+      .accounts({
+        amendment,
+        escrowMilestone: escrow,
+        contract,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const apply = () =>
+      program.methods
+        .applyMilestoneAmendment()
+        .accounts({
+          amendment,
+          escrowMilestone: escrow,
+          contract,
+          proposer: creator,
+          creatorReputation: creatorRepPDA,
+          creator,
+          vault: null,
+          mint: null,
+          creatorTokenAccount: null,
+          tokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    try {
+      await apply();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InsufficientApprovals");
+    }
+
+    await program.methods
+      .approveMilestoneAmendment()
+      .accounts({ amendment, escrowMilestone: escrow, contract, approver: vendor.publicKey })
+      .signers([vendor])
+      .rpc();
+
+    const escrowBefore = await provider.connection.getBalance(escrow);
+    await apply();
+
+    const milestone = await program.account.escrowMilestone.fetch(escrow);
+    assert.equal(milestone.amount.toNumber(), 1_500_000);
+    assert.equal(milestone.description, "Extended scope");
+    assert.equal(milestone.amendmentCount, 1);
+    assert.equal((await provider.connection.getBalance(escrow)) - escrowBefore, 500_000);
+    assert.isNull(await provider.connection.getAccountInfo(amendment));
+  });
+
+  it("Keeps same-numbered milestones of different contracts apart", async () => {
+    const vendor = await fundedWallet();
+    const firstId = contractId + 40;
+    const secondId = contractId + 41;
+    const first = await createContract(firstId, [asClient(creator), asVendor(vendor.publicKey)]);
+    const second = await createContract(secondId, [asClient(creator), asVendor(vendor.publicKey)]);
+
+    const firstEscrow = await createEscrow(first, firstId, 1, vendor.publicKey, 100_000);
+    const secondEscrow = await createEscrow(second, secondId, 1, vendor.publicKey, 200_000);
+    assert.notOk(firstEscrow.equals(secondEscrow));
+
+    // Milestones can be listed by the contract they belong to
+    const milestones = await program.account.escrowMilestone.all([
+      { memcmp: { offset: 8 + 8 + 8, bytes: second.toBase58() } },
+    ]);
+    assert.equal(milestones.length, 1);
+    assert.ok(milestones[0].publicKey.equals(secondEscrow));
+
+    try {
+      await program.methods
+        .markMilestoneComplete()
+        .accounts({
+          escrowMilestone: firstEscrow,
+          contract: second,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          marker: vendor.publicKey,
+        })
+        .signers([vendor])
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ContractMismatch");
+    }
+  });
+
+  it("Lets each side of a released milestone rate the other once", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 42;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 100_000);
+    const vendorRepPDA = reputationAddress(vendor.publicKey);
+    const receiptAddress = (rater: PublicKey, ratee: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("rating"), escrow.toBuffer(), rater.toBuffer(), ratee.toBuffer()],
+        program.programId
+      )[0];
+    const rateVendor = () =>
+      program.methods
+        .rateVendor(5)
+        .accounts({
+          vendorReputation: vendorRepPDA,
+          escrowMilestone: escrow,
+          ratingReceipt: receiptAddress(creator, vendor.publicKey),
+          rater: creator,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    try {
+      await rateVendor();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "MilestoneNotReleased");
+    }
+
+    await completeMilestone(escrow, contract, vendor);
+    await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+
+    await rateVendor();
+    assert.equal((await program.account.userReputation.fetch(vendorRepPDA)).vendorScore, 500);
+
+    // The receipt already exists
+    try {
+      await rateVendor();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "already in use");
+    }
+
+    await program.methods
+      .rateClient(4)
+      .accounts({
+        clientReputation: creatorRepPDA,
+        escrowMilestone: escrow,
+        ratingReceipt: receiptAddress(vendor.publicKey, creator),
+        rater: vendor.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([vendor])
+      .rpc();
+    const receipt = await program.account.ratingReceipt.fetch(receiptAddress(vendor.publicKey, creator));
+    assert.equal(receipt.rating, 4);
+
+    // Only the milestone's own counterparties can rate
+    const outsider = await fundedWallet();
+    try {
+      await program.methods
+        .rateVendor(1)
+        .accounts({
+          vendorReputation: vendorRepPDA,
+          escrowMilestone: escrow,
+          ratingReceipt: receiptAddress(outsider.publicKey, vendor.publicKey),
+          rater: outsider.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([outsider])
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "NotACounterparty");
+    }
+  });
+
+  it("Releases against the approvers left after a removal, but keeps owed vendors", async () => {
+    const vendor = await fundedWallet();
+    const reviewer = Keypair.generate();
+    const id = contractId + 47;
+    // The creator's weight alone carries participant changes
+    const contract = await createContract(
+      id,
+      [asClient(creator, 2), asVendor(vendor.publicKey), asClient(reviewer.publicKey)],
+      { requiredWeight: 2 }
+    );
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 100_000);
+    await completeMilestone(escrow, contract, vendor);
+
+    try {
+      await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InsufficientApprovals");
+    }
+
+    const proposeRemoval = (participant: PublicKey) =>
+      program.methods
+        .proposeParticipantChange(participant, { remove: {} })
+        .accounts({
+          participantChange: PublicKey.findProgramAddressSync(
+            [Buffer.from("participant_change"), contract.toBuffer(), participant.toBuffer()],
+            program.programId
+          )[0],
+          contract,
+          proposer: creator,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // The vendor is still owed the milestone
+    try {
+      await proposeRemoval(vendor.publicKey);
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "RecipientHasOpenMilestones");
+    }
+
+    // Without the reviewer, everyone left has approved
+    await proposeRemoval(reviewer.publicKey);
+    await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+    assert.ok((await program.account.escrowMilestone.fetch(escrow)).status.hasOwnProperty("released"));
+
+    // Once paid, the vendor can go
+    await proposeRemoval(vendor.publicKey);
+    const state = await program.account.contract.fetch(contract);
+    assert.equal(state.participants.length, 1);
+    assert.equal(state.openMilestones.length, 0);
+  });
+
+  it("Fails to cancel a milestone that holds retention from a tranche", async () => {
+    const vendor = await fundedWallet();
+    const id = contractId + 48;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+    const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+      retention: { bps: 1_000, warrantySeconds: new anchor.BN(3_600) },
+    });
+
+    await completeMilestone(escrow, contract, vendor);
+    await program.methods
+      .releasePartialEscrowFunds(new anchor.BN(500_000))
+      .accounts(solPayout(escrow, contract, vendor.publicKey))
+      .rpc();
+    assert.equal((await program.account.escrowMilestone.fetch(escrow)).retainedAmount.toNumber(), 50_000);
+
+    try {
+      await program.methods
+        .cancelEscrowMilestone()
+        .accounts({
+          escrowMilestone: escrow,
+          contract,
+          creatorReputation: creatorRepPDA,
+          creator,
+          vault: null,
+          mint: null,
+          creatorTokenAccount: null,
+          tokenProgram: null,
+        })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "RetentionStillHeld");
+    }
+  });
+
+  it("Fails to fund a milestone whose deadline has already passed", async () => {
+    const vendor = Keypair.generate();
+    const id = contractId + 49;
+    const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+
+    try {
+      await createEscrow(contract, id, 1, vendor.publicKey, 100_000, {
+        deadline: new anchor.BN((await chainTime()) - 60),
+      });
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidDeadline");
+    }
+  });
+
+  it("Keeps a contract open until its pending proposals are withdrawn", async () => {
+    const client = await fundedWallet();
+    const newcomer = await fundedWallet();
+    const newContractPDA = await createContract(contractId + 51, [asVendor(creator), asClient(client.publicKey)]);
+    const [changePDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("participant_change"), newContractPDA.toBuffer(), newcomer.publicKey.toBuffer()],
+      program.programId
+    );
+
+    // The creator's weight alone is not enough, so the change stays pending
+    await program.methods
+      .proposeParticipantChange(newcomer.publicKey, { add: { role: { client: {} }, weight: 1 } })
+      .accounts({
+        participantChange: changePDA,
+        contract: newContractPDA,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    assert.equal((await program.account.contract.fetch(newContractPDA)).pendingProposals, 1);
+
+    await program.methods
+      .cancelContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+
+    // Otherwise the proposal could be approved against a contract reusing this id
+    try {
+      await program.methods
+        .closeContract()
+        .accounts({ contract: newContractPDA, creator })
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ContractHasPendingProposals");
+    }
+
+    await program.methods
+      .withdrawParticipantChange()
+      .accounts({ participantChange: changePDA, contract: newContractPDA, proposer: creator })
+      .rpc();
+    await program.methods
+      .closeContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(newContractPDA));
+  });
+
+  it("Fails to approve an amendment once the contract is cancelled", async () => {
+    const client = await fundedWallet();
+    const newContractPDA = await createContract(contractId + 52, [asVendor(creator), asClient(client.publicKey)]);
+    await setTermsDocument(newContractPDA);
+    const amendment = amendmentAddress(newContractPDA, 2);
+
+    await program.methods
+      .proposeContractAmendment(sha256Document("amended terms"))
+      .accounts({
+        amendment,
+        contract: newContractPDA,
+        proposer: creator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .cancelContract()
+      .accounts({ contract: newContractPDA, creator })
+      .rpc();
+
+    try {
+      await program.methods
+        .approveContractAmendment()
+        .accounts({ amendment, contract: newContractPDA, approver: client.publicKey })
+        .signers([client])
+        .rpc();
+
+      assert.fail("Should have thrown error");
+    } catch (err) {
+      assert.include(err.toString(), "ContractNotActive");
+    }
+    assert.ok((await program.account.contractAmendment.fetch(amendment)).status.hasOwnProperty("pending"));
+  });
+
+  describe("disputes", () => {
+    const appealFee = 10_000_000;
+    const minStake = 10_000_000;
+    const arbiterFee = 100_000;
+    let arbiter: Keypair;
+
+    const setDisputeTimings = (evidenceSeconds: number, appealWindowSeconds = 2, resolutionSeconds = 30) =>
+      program.methods
+        .updateArbiterRegistry(new anchor.BN(appealFee), new anchor.BN(minStake), {
+          evidenceSeconds: new anchor.BN(evidenceSeconds),
+          appealWindowSeconds: new anchor.BN(appealWindowSeconds),
+          resolutionSeconds: new anchor.BN(resolutionSeconds),
+        })
+        .accounts({ registry: registryPDA, authority: creator })
+        .rpc();
+
+    const registerArbiter = async () => {
+      const wallet = await fundedWallet();
+      await program.methods
+        .registerArbiter(new anchor.BN(arbiterFee), new anchor.BN(minStake))
+        .accounts({
+          arbiter: arbiterAddress(wallet.publicKey),
+          registry: registryPDA,
+          wallet: wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+      return wallet;
+    };
+
+    // Short phases so a dispute can run to a ruling within the test
+    before("Configure the arbiter registry", async function () {
+      if (!(await program.account.arbiterRegistry.fetchNullable(registryPDA))) {
+        const [programData] = PublicKey.findProgramAddressSync(
+          [program.programId.toBuffer()],
+          new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+        );
+        await program.methods
+          .initializeArbiterRegistry(new anchor.BN(appealFee), new anchor.BN(minStake), null)
+          .accounts({
+            registry: registryPDA,
+            authority: creator,
+            program: program.programId,
+            programData,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          })
+          .rpc();
+      }
+      const registry = await program.account.arbiterRegistry.fetch(registryPDA);
+      if (!registry.authority.equals(creator) || registry.stakeMint) {
+        console.log("⚠️  Skipped: arbiter registry belongs to another authority");
+        this.skip();
+      }
+
+      await setDisputeTimings(2);
+
+      arbiter = await registerArbiter();
+    });
+
+    // Opens a dispute as `claimant`, seats the test arbiter and funds its fee
+    const openDispute = async (escrow: PublicKey, contract: PublicKey, claimant: Keypair) => {
+      const dispute = disputeAddress(escrow, (await program.account.escrowMilestone.fetch(escrow)).disputeCount);
+      await program.methods
+        .openDispute(Array.from(createHash("sha256").update("evidence bundle").digest()))
+        .accounts({
+          dispute,
+          evidenceLog: evidenceAddress(dispute),
+          escrowMilestone: escrow,
+          contract,
+          registry: registryPDA,
+          contractArbiter: null,
+          claimant: claimant.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([claimant])
+        .rpc();
+      await program.methods
+        .assignDisputePanel([arbiter.publicKey])
+        .accounts({ dispute, escrowMilestone: escrow, registry: registryPDA, authority: creator })
+        .remainingAccounts([{ pubkey: arbiterAddress(arbiter.publicKey), isSigner: false, isWritable: false }])
+        .rpc();
+      await program.methods
+        .fundDisputeFees(new anchor.BN(arbiterFee))
+        .accounts({ dispute, payer: creator, systemProgram: anchor.web3.SystemProgram.programId })
+        .rpc();
+      return dispute;
+    };
+
+    // A deciding vote passes the earlier voters that chose the same share
+    const vote = async (dispute: PublicKey, recipientBps: number, voter = arbiter, agreeing: Keypair[] = []) => {
+      await waitForChainTime((await program.account.dispute.fetch(dispute)).evidenceDeadline.toNumber());
+      await program.methods
+        .voteOnDispute(recipientBps)
+        .accounts({
+          dispute,
+          arbiterAccount: arbiterAddress(voter.publicKey),
+          registry: registryPDA,
+          arbiter: voter.publicKey,
+        })
+        .remainingAccounts(
+          agreeing.map((member) => ({ pubkey: arbiterAddress(member.publicKey), isSigner: false, isWritable: true }))
+        )
+        .signers([voter])
+        .rpc();
+    };
+
+    it("Rules on the unreleased funds and retention left after a partial release", async () => {
+      const vendor = await fundedWallet();
+      const id = contractId + 22;
+      const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+      // 10% of every payout is held back for an hour-long warranty
+      const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000, {
+        retention: { bps: 1_000, warrantySeconds: new anchor.BN(3_600) },
+      });
+
+      await completeMilestone(escrow, contract, vendor);
+      await program.methods
+        .releasePartialEscrowFunds(new anchor.BN(400_000))
+        .accounts(solPayout(escrow, contract, vendor.publicKey))
+        .rpc();
+
+      let milestone = await program.account.escrowMilestone.fetch(escrow);
+      assert.equal(milestone.releasedAmount.toNumber(), 400_000);
+      assert.equal(milestone.retainedAmount.toNumber(), 40_000);
+
+      // 600,000 unreleased plus the 40,000 retained are still at stake
+      const dispute = await openDispute(escrow, contract, vendor);
+      await vote(dispute, 5_000);
+      await waitForChainTime((await program.account.dispute.fetch(dispute)).ruledAt.toNumber() + 2);
+
+      const escrowBefore = await provider.connection.getBalance(escrow);
+      const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+
+      await program.methods
+        .executeRuling()
+        .accounts({
+          dispute,
+          escrowMilestone: escrow,
+          contract,
+          recipient: vendor.publicKey,
+          creator,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          clientReputation: creatorRepPDA,
+          claimant: vendor.publicKey,
+          appealFeeRecipient: null,
+          vault: null,
+          mint: null,
+          creatorTokenAccount: null,
+          tokenProgram: null,
+          cranker: creator,
+        })
+        .rpc();
+
+      assert.equal(escrowBefore - (await provider.connection.getBalance(escrow)), 640_000);
+      assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 320_000);
+
+      milestone = await program.account.escrowMilestone.fetch(escrow);
+      assert.ok(milestone.status.hasOwnProperty("resolved"));
+      assert.equal(milestone.retainedAmount.toNumber(), 0);
+      assert.ok((await program.account.dispute.fetch(dispute)).status.hasOwnProperty("resolved"));
+    });
+
+    it("Only lets the parties open a dispute, which freezes the milestone", async () => {
+      const vendor = await fundedWallet();
+      const outsider = await fundedWallet();
+      const id = contractId + 43;
+      const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+      const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 100_000);
+      await completeMilestone(escrow, contract, vendor);
+
+      const dispute = disputeAddress(escrow);
+      try {
+        await program.methods
+          .openDispute(Array.from(createHash("sha256").update("not my milestone").digest()))
+          .accounts({
+            dispute,
+            evidenceLog: evidenceAddress(dispute),
+            escrowMilestone: escrow,
+            contract,
+            registry: registryPDA,
+            contractArbiter: null,
+            claimant: outsider.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          })
+          .signers([outsider])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "OnlyPartiesCanDispute");
+      }
+
+      await openDispute(escrow, contract, vendor);
+      assert.ok((await program.account.escrowMilestone.fetch(escrow)).disputed);
+
+      // Fully approved, but the dispute now decides where the funds go
+      try {
+        await program.methods.releaseEscrowFunds().accounts(solPayout(escrow, contract, vendor.publicKey)).rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "MilestoneDisputed");
+      }
+    });
+
+    it("Logs evidence from either party until the evidence deadline", async () => {
+      const vendor = await fundedWallet();
+      const outsider = await fundedWallet();
+      const id = contractId + 44;
+      const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+      const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 100_000);
+      const dispute = disputeAddress(escrow);
+      const evidenceLog = evidenceAddress(dispute);
+      const submitEvidence = (submitter: Keypair, content: string, label: string) =>
+        program.methods
+          .submitEvidence(sha256Document(content), label)
+          .accounts({ evidenceLog, dispute, submitter: submitter.publicKey })
+          .signers([submitter])
+          .rpc();
+
+      // Leave enough of an evidence period to submit into
+      await setDisputeTimings(20);
+      try {
+        await program.methods
+          .openDispute(Array.from(createHash("sha256").update("evidence bundle").digest()))
+          .accounts({
+            dispute,
+            evidenceLog,
+            escrowMilestone: escrow,
+            contract,
+            registry: registryPDA,
+            contractArbiter: null,
+            claimant: vendor.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          })
+          .signers([vendor])
+          .rpc();
+      } finally {
+        await setDisputeTimings(2);
+      }
+
+      await submitEvidence(vendor, "delivery receipt", "Delivery receipt");
+      await program.methods
+        .submitEvidence(sha256Document("change request"), "Change request")
+        .accounts({ evidenceLog, dispute, submitter: creator })
+        .rpc();
+
+      const log = await program.account.evidenceLog.fetch(evidenceLog);
+      assert.equal(log.entries.length, 2);
+      assert.ok(log.entries[0].submitter.equals(vendor.publicKey));
+      assert.equal(log.entries[0].label, "Delivery receipt");
+      assert.deepEqual(log.entries[0].document.sha256[0], sha256Document("delivery receipt").sha256[0]);
+      assert.ok(log.entries[1].submitter.equals(creator));
+
+      try {
+        await submitEvidence(outsider, "hearsay", "Hearsay");
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "NotADisputeParty");
+      }
+
+      await waitForChainTime((await program.account.dispute.fetch(dispute)).evidenceDeadline.toNumber());
+      try {
+        await submitEvidence(vendor, "late invoice", "Late invoice");
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "EvidencePeriodEnded");
+      }
+    });
+
+    it("Hears an appeal before a larger panel and records the overturned vote", async () => {
+      const vendor = await fundedWallet();
+      const id = contractId + 45;
+      const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+      const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 1_000_000);
+
+      // Long enough to vote early and to appeal in time
+      await setDisputeTimings(10, 30);
+      const dispute = await openDispute(escrow, contract, vendor).finally(() => setDisputeTimings(2));
+
+      try {
+        await program.methods
+          .voteOnDispute(2_000)
+          .accounts({
+            dispute,
+            arbiterAccount: arbiterAddress(arbiter.publicKey),
+            registry: registryPDA,
+            arbiter: arbiter.publicKey,
+          })
+          .signers([arbiter])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "EvidencePeriodActive");
+      }
+
+      await vote(dispute, 2_000);
+      assert.equal((await program.account.dispute.fetch(dispute)).recipientBps, 2_000);
+
+      await program.methods
+        .appealRuling()
+        .accounts({
+          dispute,
+          escrowMilestone: escrow,
+          registry: registryPDA,
+          appellant: vendor.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([vendor])
+        .rpc();
+
+      let state = await program.account.dispute.fetch(dispute);
+      assert.ok(state.status.hasOwnProperty("appealed"));
+      assert.equal(state.firstRulingBps, 2_000);
+
+      const second = await registerArbiter();
+      const third = await registerArbiter();
+      const appealPanel = [arbiter, second, third];
+      await program.methods
+        .assignDisputePanel(appealPanel.map((member) => member.publicKey))
+        .accounts({ dispute, escrowMilestone: escrow, registry: registryPDA, authority: creator })
+        .remainingAccounts(
+          appealPanel.map((member) => ({ pubkey: arbiterAddress(member.publicKey), isSigner: false, isWritable: false }))
+        )
+        .rpc();
+      await program.methods
+        .fundDisputeFees(new anchor.BN(2 * arbiterFee))
+        .accounts({ dispute, payer: creator, systemProgram: anchor.web3.SystemProgram.programId })
+        .rpc();
+
+      // Two of three is a majority
+      await vote(dispute, 8_000, second);
+      await vote(dispute, 8_000, third, [second]);
+      state = await program.account.dispute.fetch(dispute);
+      assert.ok(state.status.hasOwnProperty("ruled"));
+      assert.equal(state.recipientBps, 8_000);
+      for (const member of [second, third]) {
+        assert.equal((await program.account.arbiter.fetch(arbiterAddress(member.publicKey))).rulingsIssued, 1);
+      }
+
+      // The appeal moved the ruling the vendor's way, so its fee comes back
+      const vendorBefore = await provider.connection.getBalance(vendor.publicKey);
+      await program.methods
+        .executeRuling()
+        .accounts({
+          dispute,
+          escrowMilestone: escrow,
+          contract,
+          recipient: vendor.publicKey,
+          creator,
+          vendorReputation: reputationAddress(vendor.publicKey),
+          clientReputation: creatorRepPDA,
+          claimant: vendor.publicKey,
+          appealFeeRecipient: vendor.publicKey,
+          vault: null,
+          mint: null,
+          creatorTokenAccount: null,
+          tokenProgram: null,
+          cranker: creator,
+        })
+        .rpc();
+      assert.equal((await provider.connection.getBalance(vendor.publicKey)) - vendorBefore, 800_000 + appealFee);
+
+      const overturnedBefore = (await program.account.arbiter.fetch(arbiterAddress(arbiter.publicKey))).rulingsOverturned;
+      await program.methods
+        .recordOverturnedRuling()
+        .accounts({ dispute, arbiter: arbiterAddress(arbiter.publicKey) })
+        .rpc();
+      const overturnedAfter = (await program.account.arbiter.fetch(arbiterAddress(arbiter.publicKey))).rulingsOverturned;
+      assert.equal(overturnedAfter - overturnedBefore, 1);
+
+      // Each overturned vote counts once
+      try {
+        await program.methods
+          .recordOverturnedRuling()
+          .accounts({ dispute, arbiter: arbiterAddress(arbiter.publicKey) })
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "RulingNotOverturned");
+      }
+    });
+
+    it("Bonds arbiter stake, slashes it and re-checks eligibility at the vote", async () => {
+      const vendor = await fundedWallet();
+      const staker = await registerArbiter();
+      const stakerAccount = arbiterAddress(staker.publicKey);
+      const id = contractId + 46;
+      const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+      const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 100_000);
+
+      const dispute = disputeAddress(escrow);
+      await program.methods
+        .openDispute(Array.from(createHash("sha256").update("evidence bundle").digest()))
+        .accounts({
+          dispute,
+          evidenceLog: evidenceAddress(dispute),
+          escrowMilestone: escrow,
+          contract,
+          registry: registryPDA,
+          contractArbiter: null,
+          claimant: vendor.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([vendor])
+        .rpc();
+      await program.methods
+        .assignDisputePanel([staker.publicKey])
+        .accounts({ dispute, escrowMilestone: escrow, registry: registryPDA, authority: creator })
+        .remainingAccounts([{ pubkey: stakerAccount, isSigner: false, isWritable: false }])
+        .rpc();
+
+      await program.methods
+        .addArbiterStake(new anchor.BN(5_000_000))
+        .accounts({
+          arbiter: stakerAccount,
+          wallet: staker.publicKey,
+          vault: null,
+          mint: null,
+          walletTokenAccount: null,
+          tokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([staker])
+        .rpc();
+      assert.equal((await program.account.arbiter.fetch(stakerAccount)).stake.toNumber(), minStake + 5_000_000);
+
+      const withdrawStake = () =>
+        program.methods
+          .withdrawArbiterStake(new anchor.BN(1_000_000))
+          .accounts({
+            arbiter: stakerAccount,
+            wallet: staker.publicKey,
+            vault: null,
+            mint: null,
+            walletTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([staker])
+          .rpc();
+
+      try {
+        await withdrawStake();
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "ArbiterStillActive");
+      }
+
+      const slash = (authority: Keypair | null, amount: number) =>
+        program.methods
+          .slashArbiter(new anchor.BN(amount))
+          .accounts({
+            arbiter: stakerAccount,
+            registry: registryPDA,
+            authority: authority ? authority.publicKey : creator,
+            vault: null,
+            mint: null,
+            authorityTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers(authority ? [authority] : [])
+          .rpc();
+
+      try {
+        await slash(vendor, 1_000_000);
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "OnlyRegistryAuthority");
+      }
+
+      // Slashing below the minimum stake leaves the arbiter ineligible
+      await slash(null, 6_000_000);
+      let account = await program.account.arbiter.fetch(stakerAccount);
+      assert.equal(account.stake.toNumber(), minStake - 1_000_000);
+      assert.equal(account.slashedAmount.toNumber(), 6_000_000);
+
+      try {
+        await vote(dispute, 10_000, staker);
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "ArbiterNotEligible");
+      }
+
+      // Going inactive starts the unbonding period
+      await program.methods
+        .updateArbiter(new anchor.BN(arbiterFee), false)
+        .accounts({ arbiter: stakerAccount, wallet: staker.publicKey })
+        .signers([staker])
+        .rpc();
+      account = await program.account.arbiter.fetch(stakerAccount);
+      assert.isFalse(account.active);
+      assert.isNotNull(account.deactivatedAt);
+
+      try {
+        await withdrawStake();
+
+        assert.fail("Should have thrown error");
+      } catch (err) {
+        assert.include(err.toString(), "StakeUnbonding");
+      }
+    });
+
+    it("Lets a milestone be disputed again once an earlier dispute lapses", async () => {
+      const vendor = await fundedWallet();
+      const id = contractId + 50;
+      const contract = await createContract(id, [asClient(creator), asVendor(vendor.publicKey)]);
+      const escrow = await createEscrow(contract, id, 1, vendor.publicKey, 100_000);
+
+      // A short resolution period so the first dispute stalls quickly
+      await setDisputeTimings(2, 2, 4);
+      const first = await openDispute(escrow, contract, vendor).finally(() => setDisputeTimings(2));
+
+      await waitForChainTime((await program.account.dispute.fetch(first)).resolutionDeadline.toNumber() + 1);
+      await program.methods
+        .resolveStalledDispute()
+        .accounts({
+          dispute: first,
+          escrowMilestone: escrow,
+          claimant: vendor.publicKey,
+          appellant: null,
+          caller: vendor.publicKey,
+        })
+        .signers([vendor])
+        .rpc();
+      assert.ok((await program.account.dispute.fetch(first)).status.hasOwnProperty("lapsed"));
+
+      const second = await openDispute(escrow, contract, payer);
+      assert.ok(second.equals(disputeAddress(escrow, 1)));
+      const state = await program.account.dispute.fetch(second);
+      assert.ok(state.status.hasOwnProperty("open"));
+      assert.equal(state.index, 1);
+      assert.ok(state.claimant.equals(creator));
+      assert.equal((await program.account.escrowMilestone.fetch(escrow)).disputeCount, 2);
+    });
+  });
+});
@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react';
 import axios from 'axios';
 import { useWallet } from '@solana/wallet-adapter-react';
 import { PublicKey } from '@solana/web3.js';
-import { approveContract } from '../solana/client';
+import { approveContract, contentDocumentRef, documentFingerprint } from '../solana/client';
 import { ensureReputationExists } from '../solana/reputation';
 
 const API_BASE = 'http://localhost:3001/api';
@@ -245,8 +245,8 @@ export function CommitView({ contractId, version, currentUserId, onRefresh, isCr
             };
             
             // Get approvals from on-chain contract
-            let approvalCount = toNumber(onChainContract.currentApprovals);
-            const requiredApprovalsCount = toNumber(onChainContract.requiredApprovals);
+            let approvalCount = toNumber(onChainContract.currentWeight);
+            const requiredApprovalsCount = toNumber(onChainContract.requiredWeight);
             const approvers = onChainContract.approvers || [];
             const participants = onChainContract.participants || [];
             
@@ -254,7 +254,7 @@ export function CommitView({ contractId, version, currentUserId, onRefresh, isCr
             // This represents their auto-approval (creator approves their own version)
             if (versionAuthorWallet) {
               const authorPubkeyStr = versionAuthorWallet;
-              const authorIsParticipant = participants.some((addr) => {
+              const authorParticipant = participants.find(({ wallet: addr }) => {
                 const addrStr = addr.toString ? addr.toString() : (addr.toBase58 ? addr.toBase58() : String(addr));
                 return addrStr === authorPubkeyStr;
               });
@@ -264,9 +264,9 @@ export function CommitView({ contractId, version, currentUserId, onRefresh, isCr
                 return addrStr === authorPubkeyStr;
               });
               
-              // If author is a participant but hasn't approved on-chain, add 1 for their auto-approval
-              if (authorIsParticipant && !authorHasApprovedOnChain) {
-                approvalCount += 1;
+              // If author is a participant but hasn't approved on-chain, add their weight for the auto-approval
+              if (authorParticipant && !authorParticipant.role?.observer && !authorHasApprovedOnChain) {
+                approvalCount += toNumber(authorParticipant.weight);
               }
             }
             
@@ -432,7 +432,9 @@ export function CommitView({ contractId, version, currentUserId, onRefresh, isCr
           console.log('Approving contract on-chain...', { 
             contractPDA: contractInfo.solana_contract_pda
           });
-          const txSignature = await approveContract(wallet, contractInfo.solana_contract_pda);
+          // Approve the text shown here; it fails if the on-chain document says otherwise
+          const documentHash = await documentFingerprint(await contentDocumentRef(version.content || ''));
+          const txSignature = await approveContract(wallet, contractInfo.solana_contract_pda, documentHash);
           console.log('Contract approved on-chain:', txSignature);
           
           // Wait a moment for blockchain confirmation
@@ -457,8 +459,8 @@ export function CommitView({ contractId, version, currentUserId, onRefresh, isCr
                 return 0;
               };
               
-              const currentApprovals = toNumber(updatedContract.currentApprovals);
-              const requiredApprovals = toNumber(updatedContract.requiredApprovals);
+              const currentApprovals = toNumber(updatedContract.currentWeight);
+              const requiredApprovals = toNumber(updatedContract.requiredWeight);
               const isCompleted = updatedContract.status?.completed !== undefined;
               
               if (isCompleted || (currentApprovals >= requiredApprovals && requiredApprovals > 0)) {
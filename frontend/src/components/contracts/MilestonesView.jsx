@@ -9,11 +9,16 @@ import {
   releaseEscrowFunds,
   cancelEscrowMilestone,
   fetchContractEscrowMilestones,
+  getContractPDA,
 } from '../../solana/escrow'
 import { initializeContract } from '../../solana/client'
 
 const API_BASE = 'http://localhost:3001/api'
 
+// Member weights come from the backend as DECIMAL(3,2) (default 0.5), while on-chain
+// approval weights are integers, so they are counted in hundredths
+const toOnChainWeight = (weight) => Math.max(1, Math.round(Number(weight ?? 1) * 100))
+
 export function MilestonesView({ contractId, contract, currentUser, isCreator }) {
   const wallet = useWallet()
   const [suggestions, setSuggestions] = useState([])
@@ -34,18 +39,16 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
       const suggestionsRes = await axios.get(`${API_BASE}/contracts/${contractId}/milestone-suggestions`)
       setSuggestions(suggestionsRes.data.milestones || [])
 
-      // Load on-chain escrow milestones if contract has Solana contract ID
-      if (contract?.solana_contract_id) {
-        const chainMilestones = await fetchContractEscrowMilestones(contract.solana_contract_id.toString())
+      // Load on-chain escrow milestones if contract has a Solana contract account
+      if (contract?.solana_contract_pda) {
+        const chainMilestones = await fetchContractEscrowMilestones(contract.solana_contract_pda)
         setOnChainMilestones(chainMilestones)
         
         // Fetch contract status from chain
         try {
-          const { fetchContractByPDA, fetchContractData } = await import('../../solana/client')
-          const onChainContract = contract.solana_contract_pda
-            ? await fetchContractByPDA(contract.solana_contract_pda)
-            : await fetchContractData(contract.solana_contract_id.toString())
-          
+          const { fetchContractByPDA } = await import('../../solana/client')
+          const onChainContract = await fetchContractByPDA(contract.solana_contract_pda)
+
           if (onChainContract) {
             setContractStatus(onChainContract.status)
           }
@@ -102,11 +105,18 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
       const membersRes = await axios.get(`${API_BASE}/contracts/${contractId}/members`)
       const members = membersRes.data.members || []
       
-      // Get all participant wallet addresses
+      // Get all participant wallet addresses and their on-chain roles and weights
       const participantAddresses = [wallet.publicKey] // Always include creator
+      const roles = {}
+      const weights = {}
       
       // Add other members if they have wallet addresses
       for (const member of members) {
+        if (member.wallet_address) {
+          const role = member.role_in_contract || 'client'
+          roles[member.wallet_address] = role
+          weights[member.wallet_address] = role === 'observer' ? 0 : toOnChainWeight(member.weight)
+        }
         if (member.wallet_address && member.wallet_address !== wallet.publicKey.toBase58()) {
           try {
             const memberPubkey = new PublicKey(member.wallet_address)
@@ -121,7 +131,10 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
       const participants = Array.from(new Set(participantAddresses.map(p => p.toBase58())))
         .map(addr => new PublicKey(addr))
       
-      const requiredApprovals = participants.length // All must approve
+      // All must approve; observers have no say
+      const requiredWeight = participants
+        .filter(p => roles[p.toBase58()] !== 'observer')
+        .reduce((sum, p) => sum + (weights[p.toBase58()] ?? toOnChainWeight()), 0)
       
       if (participants.length > 10) {
         alert('Too many participants (max 10). Please reduce the number of contract members.')
@@ -162,8 +175,12 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
           window.solana,
           contractId,
           contractContent,
-          participants.map(p => p.toBase58()),
-        requiredApprovals
+          participants.map(p => ({
+            wallet: p.toBase58(),
+            role: roles[p.toBase58()] || 'vendor', // Creator defaults to vendor
+            weight: weights[p.toBase58()] ?? toOnChainWeight(),
+          })),
+        requiredWeight
       )
         signature = result.signature
         contractPDA = result.contractPDA
@@ -271,7 +288,7 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
       const contractCreator = onChainContract.creator
 
       // Refresh on-chain milestones to get the latest count
-      const latestMilestones = await fetchContractEscrowMilestones(contract.solana_contract_id.toString())
+      const latestMilestones = await fetchContractEscrowMilestones(contract.solana_contract_pda || getContractPDA(contract.solana_contract_id.toString(), contractCreator)[0])
       
       // Get next milestone ID (use timestamp to ensure uniqueness)
       const milestoneId = Date.now()
@@ -471,7 +488,7 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
     try {
       const signature = await releaseEscrowFunds(
         wallet,
-        contract.solana_contract_id.toString(),
+        contract.solana_contract_pda,
         milestone.milestoneId.toNumber(),
         milestone.recipient.toBase58()
       )
@@ -495,7 +512,7 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
     try {
       const signature = await cancelEscrowMilestone(
         wallet,
-        contract.solana_contract_id.toString(),
+        contract.solana_contract_pda,
         milestone.milestoneId.toNumber()
       )
 
@@ -515,6 +532,8 @@ export function MilestonesView({ contractId, contract, currentUser, isCreator })
     if (status.markedComplete) return <span className="px-3 py-1 text-xs font-semibold bg-amber-100 text-amber-700 rounded-full border border-amber-200">Awaiting Approval</span>
     if (status.released) return <span className="px-3 py-1 text-xs font-semibold bg-emerald-100 text-emerald-700 rounded-full border border-emerald-200">Released</span>
     if (status.cancelled) return <span className="px-3 py-1 text-xs font-semibold bg-red-100 text-red-700 rounded-full border border-red-200">Cancelled</span>
+    if (status.expired) return <span className="px-3 py-1 text-xs font-semibold bg-red-100 text-red-700 rounded-full border border-red-200">Expired</span>
+    if (status.resolved) return <span className="px-3 py-1 text-xs font-semibold bg-gray-100 text-gray-700 rounded-full border border-gray-200">Resolved</span>
   }
 
   const hasUserApproved = (milestone) => {
@@ -12,15 +12,88 @@ function createProgram(provider: any) {
 }
 const connection = new Connection("https://api.devnet.solana.com", "confirmed");
 
+// On-chain content address of the contract document
+export type DocumentRef =
+  | { cidV0: { 0: number[] } }
+  | { cidV1: { 0: Buffer } }
+  | { sha256: { 0: number[] } };
+
+export type ParticipantRole =
+  | { client: {} }
+  | { vendor: {} }
+  | { reviewer: {} }
+  | { observer: {} };
+
+const BASE32_ALPHABET = "abcdefghijklmnopqrstuvwxyz234567";
+
+// Encodes an IPFS CID as a DocumentRef: a "Qm..." CIDv0 is its base58btc multihash, a
+// CIDv1 is given in the base32 multibase form ("b...") IPFS prints by default
+export function cidToDocumentRef(cid: string): DocumentRef {
+  if (cid.startsWith("Qm")) {
+    const multihash = Array.from(anchor.utils.bytes.bs58.decode(cid));
+    if (multihash.length !== 34) {
+      throw new Error(`Invalid CIDv0: ${cid}`);
+    }
+    return { cidV0: { 0: multihash } };
+  }
+  if (cid.startsWith("b")) {
+    const bytes: number[] = [];
+    let value = 0;
+    let bits = 0;
+    for (const char of cid.slice(1)) {
+      const index = BASE32_ALPHABET.indexOf(char);
+      if (index < 0) {
+        throw new Error(`Invalid CIDv1: ${cid}`);
+      }
+      value = ((value << 5) | index) & 0xfff;
+      bits += 5;
+      if (bits >= 8) {
+        bits -= 8;
+        bytes.push((value >> bits) & 0xff);
+      }
+    }
+    return { cidV1: { 0: Buffer.from(bytes) } };
+  }
+  throw new Error(`Unsupported CID encoding: ${cid}`);
+}
+
+// References contract text by its raw SHA-256, so anyone holding the text can check it
+export async function contentDocumentRef(content: string): Promise<DocumentRef> {
+  const digest = await crypto.subtle.digest("SHA-256", new TextEncoder().encode(content));
+  return { sha256: { 0: Array.from(new Uint8Array(digest)) } };
+}
+
+// Mirrors DocumentRef::fingerprint: SHA-256 over the variant tag and the reference bytes
+export async function documentFingerprint(document: DocumentRef): Promise<number[]> {
+  const [tag, bytes]: [number, number[]] =
+    "cidV0" in document
+      ? [0, document.cidV0[0]]
+      : "cidV1" in document
+        ? [1, Array.from(document.cidV1[0])]
+        : [2, document.sha256[0]];
+  const digest = await crypto.subtle.digest("SHA-256", new Uint8Array([tag, ...bytes]));
+  return Array.from(new Uint8Array(digest));
+}
+
+export interface Participant {
+  wallet: PublicKey;
+  role: ParticipantRole;
+  weight: number; // Approval weight; ignored for observers
+}
+
 export interface ContractData {
   contractId: anchor.BN;
   creator: PublicKey;
-  participants: PublicKey[];
-  status: { active?: {}; completed?: {}; cancelled?: {} };
-  requiredApprovals: number;
-  currentApprovals: number;
+  participants: Participant[];
+  status: { active?: {}; completed?: {}; cancelled?: {}; rejected?: {}; expired?: {} };
+  requiredWeight: number;
+  currentWeight: number;
+  vetoThreshold: number;
+  rejectors: PublicKey[];
+  approvalDeadline: anchor.BN | null;
+  orderedSigning: boolean;
   approvers: PublicKey[];
-  ipfsHash: string;
+  document: DocumentRef | null;
   createdAt: anchor.BN;
   bump: number;
 }
@@ -28,8 +101,11 @@ export interface ContractData {
 export async function initializeContract(
   wallet: anchor.Wallet,
   contractId: number,
-  participants: PublicKey[],
-  requiredApprovals: number
+  participants: Participant[],
+  requiredWeight: number,
+  vetoThreshold: number = 1,
+  approvalDeadline: anchor.BN | null = null,
+  orderedSigning: boolean = false
 ): Promise<{ signature: string; contractPDA: PublicKey }> {
   const provider = new anchor.AnchorProvider(connection, wallet, {
     commitment: "confirmed",
@@ -59,15 +135,26 @@ export async function initializeContract(
     program.programId
   );
 
+  // A contract id whose closed predecessor left amendments behind can't be reused
+  const [firstAmendmentPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("amendment"), contractPDA.toBuffer(), new anchor.BN(2).toArrayLike(Buffer, "le", 4)],
+    program.programId
+  );
+
   const tx = await program.methods
     .initializeContract(
       new anchor.BN(contractId),
       participants,
-      requiredApprovals
+      requiredWeight,
+      vetoThreshold,
+      null, // No arbiter
+      approvalDeadline,
+      orderedSigning
     )
     .accounts({
       contract: contractPDA,
       creatorReputation: creatorRepPDA,
+      firstAmendment: firstAmendmentPDA,
       creator: wallet.publicKey,
       systemProgram: anchor.web3.SystemProgram.programId,
     })
@@ -76,9 +163,12 @@ export async function initializeContract(
   return { signature: tx, contractPDA };
 }
 
+// Approvals are bound to a document version; `documentHash` defaults to the
+// fingerprint of the contract's current on-chain document
 export async function approveContract(
   wallet: anchor.Wallet,
-  contractPDA: string | PublicKey
+  contractPDA: string | PublicKey,
+  documentHash?: number[]
 ): Promise<string> {
   const provider = new anchor.AnchorProvider(connection, wallet, {
     commitment: "confirmed",
@@ -92,8 +182,9 @@ export async function approveContract(
     : contractPDA;
   
   // First, verify the contract exists on-chain
+  let contract: ContractData;
   try {
-    await program.account.contract.fetch(contractPDAKey);
+    contract = (await program.account.contract.fetch(contractPDAKey)) as unknown as ContractData;
     console.log('Contract account exists on-chain');
   } catch (e) {
     const errorMessage = (e as any)?.message || String(e);
@@ -118,8 +209,15 @@ export async function approveContract(
     await ensureReputationExists(wallet);
   }
 
+  if (!documentHash) {
+    if (!contract.document) {
+      throw new Error('Contract has no document to approve yet.');
+    }
+    documentHash = await documentFingerprint(contract.document);
+  }
+
   const tx = await program.methods
-    .approveContract()
+    .approveContract(documentHash)
     .accounts({
       contract: contractPDAKey,
       approverReputation: approverRepPDA,
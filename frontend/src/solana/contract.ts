@@ -2,6 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program, AnchorProvider } from "@coral-xyz/anchor";
 import { Connection, PublicKey, Transaction, VersionedTransaction } from "@solana/web3.js";
 import { ensureReputationExists } from "./reputation";
+import { contentDocumentRef } from "./client";
 import idl from "../../../agreed_contracts/target/idl/agreed_contracts.json";
 import axios from "axios";
 
@@ -52,8 +53,8 @@ export async function initializeContractOnChain(
   walletAdapter: any, // Solana wallet adapter from window.solana
   contractId: string,
   content: string,
-  participantWallets: string[],
-  requiredApprovals: number
+  participantWallets: { wallet: string; role: string; weight: number }[],
+  requiredWeight: number
 ): Promise<{ signature: string; contractPDA: string; ipfsHash: string }> {
   try {
     // Upload content to IPFS
@@ -75,9 +76,10 @@ export async function initializeContractOnChain(
     console.log("Participant wallets:", participantWallets);
 
     // Convert participant addresses to PublicKeys
-    const participants = participantWallets.map(addr => {
+    const participants = participantWallets.map(({ wallet: addr, role, weight }) => {
       try {
-        return new PublicKey(addr);
+        // Backend roles are lowercase ("client", "vendor"), matching the on-chain enum
+        return { wallet: new PublicKey(addr), role: { [role.toLowerCase()]: {} }, weight };
       } catch (e) {
         console.error("Invalid wallet address:", addr, e);
         throw new Error(`Invalid participant wallet address: ${addr}`);
@@ -119,19 +121,38 @@ export async function initializeContractOnChain(
       program.programId
     );
 
+    // Initialization fails if a closed contract with this id left amendments behind
+    const [firstAmendmentPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("amendment"), contractPDA.toBuffer(), new anchor.BN(2).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+
+    // Approvals are bound to the document, so it is set in the same transaction
+    const document = await contentDocumentRef(content);
+    const setDocumentIx = await program.methods
+      .setContractDocument(document)
+      .accounts({ contract: contractPDA, updater: wallet.publicKey })
+      .instruction();
+
     // Initialize contract on-chain
     const tx = await program.methods
       .initializeContract(
         numericIdBN,
         participants,
-        requiredApprovals
+        requiredWeight,
+        1, // Any rejection vetoes
+        null, // No arbiter
+        null, // No approval deadline
+        false // Any signing order
       )
       .accounts({
         contract: contractPDA,
         creatorReputation: creatorReputationPDA,
+        firstAmendment: firstAmendmentPDA,
         creator: wallet.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
+      .postInstructions([setDocumentIx])
       .rpc();
 
     console.log("Contract initialized on-chain:", tx);
@@ -15,11 +15,12 @@ const connection = new Connection("https://api.devnet.solana.com", "confirmed");
 export interface EscrowMilestoneData {
   milestoneId: anchor.BN;
   contractId: anchor.BN;
+  contract: PublicKey;
   description: string;
   amount: anchor.BN;
   recipient: PublicKey;
   deadline: anchor.BN;
-  status: { pending?: {}; funded?: {}; markedComplete?: {}; released?: {}; cancelled?: {} };
+  status: { pending?: {}; funded?: {}; markedComplete?: {}; released?: {}; cancelled?: {}; expired?: {}; resolved?: {} };
   approvalsRequired: number;
   approvals: PublicKey[];
   markedCompleteBy: PublicKey | null;
@@ -28,21 +29,16 @@ export interface EscrowMilestoneData {
   bump: number;
 }
 
-// Derive escrow milestone PDA
-export function getEscrowMilestonePDA(contractId: string | number, milestoneId: number): [PublicKey, number] {
-  // Convert contractId to BN properly
-  const contractIdBigInt = typeof contractId === 'string' 
-    ? BigInt(contractId)
-    : BigInt(contractId);
-  
-  const contractBuffer = Buffer.alloc(8);
-  contractBuffer.writeBigUInt64LE(contractIdBigInt);
-  const contractIdBN = new anchor.BN(contractBuffer, 'le');
-  
+// Derive escrow milestone PDA (scoped to the parent contract account)
+export function getEscrowMilestonePDA(contractPDA: string | PublicKey, milestoneId: number): [PublicKey, number] {
+  const contractKey = typeof contractPDA === 'string'
+    ? new PublicKey(contractPDA)
+    : contractPDA;
+
   const [pda, bump] = PublicKey.findProgramAddressSync(
     [
       Buffer.from("escrow"),
-      contractIdBN.toArrayLike(Buffer, "le", 8),
+      contractKey.toBuffer(),
       new anchor.BN(milestoneId).toArrayLike(Buffer, "le", 8),
     ],
     PROGRAM_ID
@@ -101,10 +97,10 @@ export async function initializeEscrowMilestone(
   });
   const program = createProgram(provider);
 
-  const [escrowPDA] = getEscrowMilestonePDA(contractId, milestoneId);
   const contractPDA = contractPDAOverride
     ? new PublicKey(contractPDAOverride)
     : getContractPDA(contractId, contractCreator)[0];
+  const [escrowPDA] = getEscrowMilestonePDA(contractPDA, milestoneId);
   
   // Use program ID from IDL to ensure consistency with reputation account creation
   const [creatorReputationPDA] = getReputationPDA(wallet.publicKey, program.programId);
@@ -137,7 +133,11 @@ export async function initializeEscrowMilestone(
         description,
         new anchor.BN(amountInLamports.toString()),
         recipient,
-        deadlineBN
+        deadlineBN,
+        null, // no late-delivery penalty
+        null, // no early-delivery bonus
+        null, // no retention
+        [] // recipient receives the full payout
       )
       .accounts({
         escrowMilestone: escrowPDA,
@@ -169,10 +169,10 @@ export async function markMilestoneComplete(
   });
   const program = createProgram(provider);
 
-  const [escrowPDA] = getEscrowMilestonePDA(contractId, milestoneId);
   const contractPDA = contractPDAOverride
     ? new PublicKey(contractPDAOverride)
     : getContractPDA(contractId, contractCreator)[0];
+  const [escrowPDA] = getEscrowMilestonePDA(contractPDA, milestoneId);
 
   try {
     // Ensure vendor (marker) reputation account exists before marking complete
@@ -211,10 +211,10 @@ export async function approveMilestoneRelease(
   });
   const program = createProgram(provider);
 
-  const [escrowPDA] = getEscrowMilestonePDA(contractId, milestoneId);
   const contractPDA = contractPDAOverride
     ? new PublicKey(contractPDAOverride)
     : getContractPDA(contractId, contractCreator)[0];
+  const [escrowPDA] = getEscrowMilestonePDA(contractPDA, milestoneId);
   
   // Use program ID from IDL to ensure consistency
   const [approverReputationPDA] = getReputationPDA(wallet.publicKey, program.programId);
@@ -246,7 +246,7 @@ export async function approveMilestoneRelease(
 // Release escrow funds (anyone can call if approvals met)
 export async function releaseEscrowFunds(
   wallet: anchor.Wallet,
-  contractId: string,
+  contractPDA: string | PublicKey,
   milestoneId: number,
   recipientAddress: string,
   creatorAddress?: string
@@ -256,7 +256,7 @@ export async function releaseEscrowFunds(
   });
   const program = createProgram(provider);
 
-  const [escrowPDA] = getEscrowMilestonePDA(contractId, milestoneId);
+  const [escrowPDA] = getEscrowMilestonePDA(contractPDA, milestoneId);
   const recipient = new PublicKey(recipientAddress);
 
   try {
@@ -290,7 +290,9 @@ export async function releaseEscrowFunds(
       .releaseEscrowFunds()
       .accounts({
         escrowMilestone: escrowPDA,
+        contract: new PublicKey(contractPDA),
         recipient: recipient,
+        creator: creator,
       })
       .rpc();
 
@@ -305,7 +307,7 @@ export async function releaseEscrowFunds(
 // Cancel escrow milestone (creator only)
 export async function cancelEscrowMilestone(
   wallet: anchor.Wallet,
-  contractId: string,
+  contractPDA: string | PublicKey,
   milestoneId: number
 ): Promise<string> {
   const provider = new anchor.AnchorProvider(connection, wallet, {
@@ -313,13 +315,14 @@ export async function cancelEscrowMilestone(
   });
   const program = createProgram(provider);
 
-  const [escrowPDA] = getEscrowMilestonePDA(contractId, milestoneId);
+  const [escrowPDA] = getEscrowMilestonePDA(contractPDA, milestoneId);
 
   try {
     const tx = await program.methods
       .cancelEscrowMilestone()
       .accounts({
         escrowMilestone: escrowPDA,
+        contract: new PublicKey(contractPDA),
         creator: wallet.publicKey,
       })
       .rpc();
@@ -334,7 +337,7 @@ export async function cancelEscrowMilestone(
 
 // Fetch escrow milestone data from chain
 export async function fetchEscrowMilestone(
-  contractId: number,
+  contractPDA: string | PublicKey,
   milestoneId: number
 ): Promise<EscrowMilestoneData | null> {
   const provider = new anchor.AnchorProvider(
@@ -344,7 +347,7 @@ export async function fetchEscrowMilestone(
   );
   const program = createProgram(provider);
 
-  const [escrowPDA] = getEscrowMilestonePDA(contractId, milestoneId);
+  const [escrowPDA] = getEscrowMilestonePDA(contractPDA, milestoneId);
 
   try {
     const escrowData = await program.account.escrowMilestone.fetch(escrowPDA);
@@ -355,9 +358,9 @@ export async function fetchEscrowMilestone(
   }
 }
 
-// Fetch all escrow milestones for a contract
+// Fetch all escrow milestones for a contract account
 export async function fetchContractEscrowMilestones(
-  contractId: string
+  contractPDA: string | PublicKey
 ): Promise<EscrowMilestoneData[]> {
   const provider = new anchor.AnchorProvider(
     connection,
@@ -367,20 +370,16 @@ export async function fetchContractEscrowMilestones(
   const program = createProgram(provider);
 
   try {
-    // Convert contractId string to BN properly
-    // Split into high and low parts for large numbers
-    const contractIdBigInt = BigInt(contractId);
-    const buffer = Buffer.alloc(8);
-    buffer.writeBigUInt64LE(contractIdBigInt);
-    const contractIdBN = new anchor.BN(buffer, 'le');
+    const contractKey = typeof contractPDA === 'string'
+      ? new PublicKey(contractPDA)
+      : contractPDA;
 
+    // contract_ids are only unique per creator, so filter on the contract account itself
     const escrows = await program.account.escrowMilestone.all([
       {
         memcmp: {
-          offset: 8 + 8, // discriminator + milestone_id
-          bytes: anchor.utils.bytes.bs58.encode(
-            contractIdBN.toArrayLike(Buffer, "le", 8)
-          ),
+          offset: 8 + 8 + 8, // discriminator + milestone_id + contract_id
+          bytes: contractKey.toBase58(),
         },
       },
     ]);